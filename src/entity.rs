@@ -1,7 +1,16 @@
+use geom::TupleTriangle;
 use material::Material;
-use mesh::DeinterleavedIndexedMeshBuf;
+use mesh::{
+    ColoredVertex, DeinterleavedIndexedMeshBuf, DeinterleavedIndexedMeshBufIter, IndexedMesh, Mesh,
+};
 use std::rc::Rc;
+use transform::{Transform, TransformedVertices};
 
+/// A named mesh with a material, placed in the world with a model transform.
+///
+/// Entities implement [`Mesh`](trait.Mesh.html) in world space, that is, vertices
+/// obtained through the trait have the model transform applied, while the
+/// referenced mesh itself stays in model space and can be shared.
 #[derive(Clone)]
 pub struct Entity {
     pub name: String,
@@ -10,7 +19,9 @@ pub struct Entity {
     /// The reference itself can be set to a new material however.
     pub material: Rc<Material>,
     /// The geometry of the entity, represented as an indexed triangle mesh.
-    pub mesh: Rc<DeinterleavedIndexedMeshBuf>, // TODO model transform if need be
+    pub mesh: Rc<DeinterleavedIndexedMeshBuf>,
+    /// Transforms the mesh from model space into world space.
+    pub transform: Transform,
 }
 
 impl Entity {
@@ -23,15 +34,92 @@ impl Entity {
             mesh: Rc::new(mesh),
             name: name.into(),
             material,
+            transform: Transform::identity(),
         }
     }
+
+    /// Sets the model transform, consuming and returning the entity.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Creates an instance of this entity with a different name and transform.
+    ///
+    /// The instance shares both mesh and material with this entity, so many
+    /// instances can be placed in a scene without duplicating geometry. The
+    /// material of the returned entity can be replaced without affecting others.
+    pub fn instantiate<S: Into<String>>(&self, name: S, transform: Transform) -> Self {
+        self.instantiate_with_material(name, transform, Rc::clone(&self.material))
+    }
+
+    /// Creates an instance of this entity that shares the mesh, but has a
+    /// different name, transform and material.
+    pub fn instantiate_with_material<S: Into<String>>(
+        &self,
+        name: S,
+        transform: Transform,
+        material: Rc<Material>,
+    ) -> Self {
+        Entity {
+            name: name.into(),
+            material,
+            mesh: Rc::clone(&self.mesh),
+            transform,
+        }
+    }
+
+    /// Checks if this entity shares its mesh with the given other entity.
+    pub fn is_instance_of(&self, other: &Entity) -> bool {
+        Rc::ptr_eq(&self.mesh, &other.mesh)
+    }
+
+    /// Creates a new mesh in world space by applying the model transform to
+    /// positions and normals of the model space mesh.
+    ///
+    /// The indexing of the mesh is preserved.
+    pub fn bake_transform(&self) -> DeinterleavedIndexedMeshBuf {
+        self.mesh.transformed(&self.transform)
+    }
+}
+
+impl<'a> Mesh<'a> for Entity {
+    type Vertex = ColoredVertex;
+    type VertexIter = TransformedVertices<DeinterleavedIndexedMeshBufIter<'a>>;
+
+    /// Iterates over the vertices of the mesh in world space.
+    fn vertices(&'a self) -> Self::VertexIter {
+        TransformedVertices::new(self.transform, self.mesh.vertices())
+    }
+
+    fn vertex_count(&'a self) -> usize {
+        self.mesh.vertex_count()
+    }
+}
+
+impl<'a> IndexedMesh<'a> for Entity {
+    fn triangle_indices(&'a self, triangle_idx: usize) -> [u32; 3] {
+        self.mesh.triangle_indices(triangle_idx)
+    }
+
+    /// Gets the triangle with the given number in world space.
+    fn triangle_at(&'a self, triangle_idx: usize) -> TupleTriangle<ColoredVertex> {
+        let first = triangle_idx * 3;
+        TupleTriangle::new(
+            self.transform.transform_vertex(self.mesh.vertex_at(first)),
+            self.transform
+                .transform_vertex(self.mesh.vertex_at(first + 1)),
+            self.transform
+                .transform_vertex(self.mesh.vertex_at(first + 2)),
+        )
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use geom::{Normal, Position, Triangle, Vec3};
     use material::MaterialBuilder;
-    use mesh::Mesh;
 
     #[test]
     fn test_share_material() {
@@ -54,6 +142,56 @@ mod test {
         assert_eq!(1, Rc::strong_count(&ent2.material));
     }
 
+    #[test]
+    fn test_world_space() {
+        let entity = Entity::new(make_mesh(), "Moved", make_material()).with_transform(
+            Transform::from_translation(Vec3::new(0.0, 5.0, 0.0))
+                * Transform::from_scale(Vec3::new(2.0, 1.0, 1.0)),
+        );
+
+        let first = entity.vertices().next().unwrap();
+        assert_eq!(first.position(), Vec3::new(-2.0, 4.0, -1.0));
+        assert_eq!(first.normal(), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(entity.triangles().count(), 2);
+        assert_eq!(entity.triangle_indices(1), [0, 1, 2]);
+        let triangle = entity.triangle_at(0);
+        let (moved, _, _) = triangle.vertices();
+        assert_eq!(moved.position(), first.position());
+
+        let bounds = entity.calculate_bounds();
+        assert_eq!(bounds.min, Vec3::new(-200.0, -95.0, -100.0));
+        assert_eq!(bounds.max, Vec3::new(200.0, 105.0, 100.0));
+
+        let baked = entity.bake_transform();
+        assert_eq!(baked.indices, entity.mesh.indices);
+        assert_eq!(&baked.positions[0..3], &[2.0, 6.0, 1.0]);
+        assert_eq!(&baked.normals[0..3], &[1.0, 0.0, 0.0]);
+        assert_eq!(baked.texcoords, entity.mesh.texcoords);
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let original = Entity::new(make_mesh(), "Original", make_material());
+        let mut instance = original.instantiate("Instance", Transform::from_uniform_scale(0.5));
+
+        assert!(instance.is_instance_of(&original));
+        assert!(Rc::ptr_eq(&instance.material, &original.material));
+        assert_eq!(2, Rc::strong_count(&original.mesh));
+
+        let first = instance.vertices().next().unwrap();
+        assert_eq!(first.position(), Vec3::new(-0.5, -0.5, -0.5));
+
+        instance.material = make_material();
+        assert!(!Rc::ptr_eq(&instance.material, &original.material));
+        assert!(instance.is_instance_of(&original));
+
+        let red = make_material();
+        let red_instance =
+            original.instantiate_with_material("Red", Transform::identity(), Rc::clone(&red));
+        assert!(red_instance.is_instance_of(&original));
+        assert!(Rc::ptr_eq(&red_instance.material, &red));
+    }
+
     fn make_material() -> Rc<Material> {
         Rc::new(MaterialBuilder::new().build())
     }
@@ -70,6 +208,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![3, 4, 5, 0, 1, 2],
+            ..Default::default()
         }
     }
 }
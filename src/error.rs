@@ -0,0 +1,114 @@
+use mesh::LayoutError;
+use obj::LoadError;
+use ply::PlyLoadError;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Any error that can occur in this crate, so callers processing many assets
+/// can handle them uniformly, e.g. by logging and skipping broken assets.
+///
+/// The more specific error types of the crate convert into this type, so they
+/// can be propagated with `?`.
+///
+/// ```no_run
+/// use aitios_scene::{load_obj, Error};
+///
+/// fn count_vertices(path: &str) -> Result<usize, Error> {
+///     let mut count = 0;
+///     for entity in load_obj(path)? {
+///         for vertex in entity.mesh.try_vertices() {
+///             vertex?;
+///             count += 1;
+///         }
+///     }
+///     Ok(count)
+/// }
+///
+/// for path in &["/tmp/a.obj", "/tmp/b.obj"] {
+///     match count_vertices(path) {
+///         Ok(count) => println!("{}: {} vertices", path, count),
+///         Err(err) => eprintln!("Skipping {}: {}", path, err),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// A vertex was accessed at a position past the end of the index vector.
+    NoSuchIndex {
+        index_index: usize,
+        index_count: usize,
+    },
+    /// The indices end with an incomplete triangle starting at the given
+    /// position in the index vector.
+    IncompleteTriangle { index_index: usize },
+    /// Mesh data was inconsistent, e.g. an index referenced a vertex that does
+    /// not exist or an attribute had too few values.
+    Layout(LayoutError),
+    /// Loading an OBJ file failed.
+    Obj(LoadError),
+    /// Loading a PLY file failed.
+    Ply(PlyLoadError),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NoSuchIndex {
+                index_index,
+                index_count,
+            } => write!(
+                f,
+                "Accessed index {} but mesh only has {} indices",
+                index_index, index_count
+            ),
+            Error::IncompleteTriangle { index_index } => write!(
+                f,
+                "Indices end with an incomplete triangle at index {}",
+                index_index
+            ),
+            Error::Layout(ref cause) => write!(f, "Inconsistent mesh: {}", cause),
+            Error::Obj(ref cause) => cause.fmt(f),
+            Error::Ply(ref cause) => cause.fmt(f),
+            Error::Io(ref cause) => cause.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Layout(ref cause) => Some(cause),
+            Error::Obj(ref cause) => Some(cause),
+            Error::Ply(ref cause) => Some(cause),
+            Error::Io(ref cause) => Some(cause),
+            _ => None,
+        }
+    }
+}
+
+impl From<LayoutError> for Error {
+    fn from(err: LayoutError) -> Self {
+        Error::Layout(err)
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(err: LoadError) -> Self {
+        Error::Obj(err)
+    }
+}
+
+impl From<PlyLoadError> for Error {
+    fn from(err: PlyLoadError) -> Self {
+        Error::Ply(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
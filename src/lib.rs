@@ -1,16 +1,45 @@
 //!
 //! Provides types for representing scenes, including:
-//! * The [`Mesh`](trait.Mesh.html) trait for types that represent triangle meshes,
+//! * The [`Mesh`](trait.Mesh.html) trait for types that represent triangle meshes and [`MeshMut`](trait.MeshMut.html) for editing them in place,
+//! * [`DeinterleavedIndexedMeshBuf`](struct.DeinterleavedIndexedMeshBuf.html) and [`InterleavedIndexedMeshBuf`](struct.InterleavedIndexedMeshBuf.html) as indexed triangle meshes with one vector per attribute or with interleaved vertices ready for the GPU, and [`DeinterleavedIndexedMeshSlice`](struct.DeinterleavedIndexedMeshSlice.html) and [`StridedMeshSlice`](struct.StridedMeshSlice.html) as views of borrowed data,
+//! * [`Topology`](struct.Topology.html) for walking from triangles to their neighbours and finding boundary and non-manifold edges,
+//! * [`SurfaceSampler`](struct.SurfaceSampler.html) for drawing reproducible, uniformly distributed random points from the surface of a mesh,
 //! * The [`Material`](struct.Material.html) and [`MaterialBuilder`](struct.MaterialBuilder.html) types for OBJ-compatible materials,
-//! * [`Entity`](struct.Entity.html) as a standard struct for a named mesh with a referenced material.
+//! * [`Entity`](struct.Entity.html) as a standard struct for a named mesh with a referenced material and a [`Transform`](struct.Transform.html),
+//! * [`SyncEntity`](struct.SyncEntity.html) as a thread-safe variant of `Entity`,
+//! * [`Scene`](struct.Scene.html) as a container for entities and the materials they share,
+//! * [`Node`](struct.Node.html) for hierarchical scene graphs that can be flattened into entities,
+//! * [`load_obj`](fn.load_obj.html) and [`save_obj`](fn.save_obj.html) for loading and saving entities in OBJ format,
+//! * [`load_ply`](fn.load_ply.html) and [`save_ply`](fn.save_ply.html) for loading and saving meshes with all their attributes, including colors and custom ones, in PLY format,
+//! * [`Error`](enum.Error.html) as a crate-wide error type, with checked mesh access like [`get_vertex`](struct.DeinterleavedIndexedMeshBuf.html#method.get_vertex) and [`try_triangles`](struct.DeinterleavedIndexedMeshBuf.html#method.try_triangles) to skip broken assets instead of panicking.
+//!
+//! With the `rayon` feature enabled, meshes also offer parallel iteration over vertices and triangles.
 //!
 extern crate aitios_geom as geom;
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 extern crate tobj;
 
 mod entity;
+mod error;
 mod material;
+mod math;
 mod mesh;
+mod node;
+mod obj;
+mod ply;
+mod scene;
+mod sync;
+mod transform;
 
 pub use entity::Entity;
+pub use error::Error;
 pub use material::{Material, MaterialBuilder};
 pub use mesh::*;
+pub use node::{Node, NodeVisitor};
+pub use obj::{load_obj, save_obj, LoadError, ObjExporter, ObjLoader};
+pub use ply::{load_ply, save_ply, PlyLoadError};
+pub use scene::Scene;
+pub use sync::{from_sync_entities, to_sync_entities, SyncEntity};
+pub use transform::{Transform, TransformedVertices};
@@ -54,6 +54,9 @@ const EMISSIVE_MAP_KEY: &str = "map_Ke";
 /// | `metallic_map`  | `map_Pm`, Metallic           | Metallicity           |
 /// | `sheen_map`     | `map_Ps`, Sheen              | —                     |
 /// | `emissive_map`  | `map_Ke`, Emissive           | Emission              |
+///
+/// Maps sample texture coordinate set 0 unless declared otherwise with
+/// [`MaterialBuilder::uv_set`](struct.MaterialBuilder.html#method.uv_set).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Material {
     name: String,
@@ -85,6 +88,9 @@ pub struct Material {
     /// | map_Ps        | Sheen                | —                 |
     /// | map_Ke        | Emissive             | Emission          |
     maps: HashMap<String, PathBuf>,
+    /// Maps the keys of maps that sample a texture coordinate set other than
+    /// set 0 against the number of the set.
+    uv_sets: HashMap<String, usize>,
 }
 
 impl Material {
@@ -152,6 +158,12 @@ impl Material {
     pub fn maps(&self) -> &HashMap<String, PathBuf> {
         &self.maps
     }
+
+    /// Gets the texture coordinate set sampled by the map with the given
+    /// MTL key, e.g. `map_Kd`. Defaults to set 0.
+    pub fn uv_set(&self, map_key: &str) -> usize {
+        self.uv_sets.get(map_key).cloned().unwrap_or(0)
+    }
 }
 
 /// Creates new and derived materials.
@@ -189,6 +201,7 @@ impl MaterialBuilder {
             mat: Material {
                 name: String::new(),
                 maps: HashMap::new(),
+                uv_sets: HashMap::new(),
             },
         }
     }
@@ -283,6 +296,31 @@ impl MaterialBuilder {
         self
     }
 
+    /// Declares that the map with the given MTL key, e.g. `map_Kd`, samples
+    /// the texture coordinate set with the given number instead of set 0.
+    ///
+    /// ```
+    /// use aitios_scene::MaterialBuilder;
+    ///
+    /// let material = MaterialBuilder::new()
+    ///     .diffuse_color_map("/tmp/textures/bricks.png")
+    ///     .emissive_map("/tmp/textures/baked_rust.png")
+    ///     .uv_set("map_Ke", 1)
+    ///     .build();
+    ///
+    /// assert_eq!(material.uv_set("map_Kd"), 0);
+    /// assert_eq!(material.uv_set("map_Ke"), 1);
+    /// ```
+    pub fn uv_set<S: Into<String>>(mut self, map_key: S, set: usize) -> Self {
+        let map_key = map_key.into();
+        if set == 0 {
+            self.mat.uv_sets.remove(&map_key);
+        } else {
+            self.mat.uv_sets.insert(map_key, set);
+        }
+        self
+    }
+
     pub fn build(self) -> Material {
         self.mat
     }
@@ -334,5 +372,11 @@ mod test {
         assert_ne!(mat1, other_diffuse);
         assert_ne!(mat1, other_normal);
         assert_ne!(other_diffuse, other_normal);
+
+        let other_uv_set = MaterialBuilder::from(&mat1).uv_set("map_Kd", 1).build();
+        let default_uv_set = MaterialBuilder::from(&mat1).uv_set("map_Kd", 0).build();
+
+        assert_ne!(mat1, other_uv_set);
+        assert_eq!(mat1, default_uv_set);
     }
 }
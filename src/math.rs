@@ -0,0 +1,58 @@
+//! Vector helpers used throughout the crate that operate on `Vec3` by
+//! component so they do not depend on a specific linear algebra backend.
+
+use geom::Vec3;
+
+pub fn dot(a: Vec3, b: Vec3) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x,
+    )
+}
+
+pub fn length(v: Vec3) -> f32 {
+    dot(v, v).sqrt()
+}
+
+/// Scales the vector to unit length, zero vectors are returned as is.
+pub fn normalize(v: Vec3) -> Vec3 {
+    let len = length(v);
+    if len > 0.0 {
+        v * len.recip()
+    } else {
+        v
+    }
+}
+
+/// Area of the triangle with the given corners.
+pub fn triangle_area(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    0.5 * length(cross(b - a, c - a))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cross_right_handed() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+
+        assert_eq!(cross(x, y), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(dot(x, y), 0.0);
+        assert_eq!(
+            normalize(Vec3::new(0.0, 3.0, 4.0)),
+            Vec3::new(0.0, 0.6, 0.8)
+        );
+        assert_eq!(
+            normalize(Vec3::new(0.0, 0.0, 0.0)),
+            Vec3::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(triangle_area(Vec3::new(0.0, 0.0, 0.0), x * 2.0, y), 1.0);
+    }
+}
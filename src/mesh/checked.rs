@@ -0,0 +1,222 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::LayoutError;
+use super::slice::DeinterleavedIndexedMeshSlice;
+use super::vertex::ColoredVertex;
+use error::Error;
+use geom::TupleTriangle;
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Checked version of [`vertex_at`](#method.vertex_at) that returns an
+    /// error instead of panicking if the index or the mesh data is malformed.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, Error};
+    ///
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     indices: vec![0, 1, 7],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(mesh.get_vertex(1).is_ok());
+    /// assert!(mesh.get_vertex(2).is_err());
+    /// match mesh.get_vertex(3) {
+    ///     Err(Error::NoSuchIndex { index_count: 3, .. }) => (),
+    ///     _ => panic!("Expected missing index"),
+    /// }
+    /// ```
+    pub fn get_vertex(&self, index_index: usize) -> Result<ColoredVertex, Error> {
+        self.as_standard_slice().get_vertex(index_index)
+    }
+
+    /// Checked version of [`vertices`](trait.Mesh.html#tymethod.vertices)
+    /// that yields an error for each vertex that cannot be accessed.
+    pub fn try_vertices(&self) -> TryVertexIter<'_> {
+        self.as_standard_slice().try_vertices()
+    }
+
+    /// Checked version of [`triangles`](trait.Mesh.html#method.triangles)
+    /// that yields an error for each triangle with a vertex that cannot be
+    /// accessed. Instead of silently ignoring an incomplete triangle at the
+    /// end of the indices, a last `Error::IncompleteTriangle` is yielded.
+    pub fn try_triangles(&self) -> TryTriangleIter<'_> {
+        self.as_standard_slice().try_triangles()
+    }
+}
+
+impl<'a> DeinterleavedIndexedMeshSlice<'a> {
+    /// Checked version of [`vertex_at`](#method.vertex_at), see
+    /// [`DeinterleavedIndexedMeshBuf::get_vertex`](struct.DeinterleavedIndexedMeshBuf.html#method.get_vertex).
+    pub fn get_vertex(&self, index_index: usize) -> Result<ColoredVertex, Error> {
+        let index = *self.indices.get(index_index).ok_or(Error::NoSuchIndex {
+            index_index,
+            index_count: self.indices.len(),
+        })?;
+
+        let idx = index as usize;
+        let vertex_count = self.positions.len() / 3;
+        if idx >= vertex_count {
+            return Err(Error::Layout(LayoutError::IndexOutOfRange {
+                index_index,
+                index,
+                vertex_count,
+            }));
+        }
+
+        let check = |attribute: &'static str, values: &[f32], stride: usize| {
+            if !values.is_empty() && values.len() < (idx + 1) * stride {
+                Err(LayoutError::AttributeLength {
+                    attribute,
+                    len: values.len(),
+                    expected: vertex_count * stride,
+                })
+            } else {
+                Ok(())
+            }
+        };
+        check("normal", self.normals, 3)?;
+        check("texcoord", self.texcoords, 2)?;
+        check("color", self.colors, 4)?;
+
+        Ok(self.vertex_at(index_index))
+    }
+
+    /// Checked version of [`vertices`](trait.Mesh.html#tymethod.vertices)
+    /// that yields an error for each vertex that cannot be accessed.
+    pub fn try_vertices(&self) -> TryVertexIter<'a> {
+        TryVertexIter {
+            mesh: self.clone(),
+            next_indices_idx: 0,
+        }
+    }
+
+    /// Checked version of [`triangles`](trait.Mesh.html#method.triangles),
+    /// see [`DeinterleavedIndexedMeshBuf::try_triangles`](struct.DeinterleavedIndexedMeshBuf.html#method.try_triangles).
+    pub fn try_triangles(&self) -> TryTriangleIter<'a> {
+        TryTriangleIter {
+            mesh: self.clone(),
+            next_indices_idx: 0,
+        }
+    }
+}
+
+/// Iterates over the vertices of a borrowed mesh, yielding
+/// errors for vertices that cannot be accessed.
+#[derive(Clone)]
+pub struct TryVertexIter<'a> {
+    mesh: DeinterleavedIndexedMeshSlice<'a>,
+    next_indices_idx: usize,
+}
+
+impl<'a> Iterator for TryVertexIter<'a> {
+    type Item = Result<ColoredVertex, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next_indices_idx;
+
+        if idx >= self.mesh.indices.len() {
+            None
+        } else {
+            self.next_indices_idx += 1;
+            Some(self.mesh.get_vertex(idx))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.mesh.indices.len() - self.next_indices_idx;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Iterates over the triangles of a borrowed mesh, yielding
+/// errors for triangles that cannot be accessed.
+#[derive(Clone)]
+pub struct TryTriangleIter<'a> {
+    mesh: DeinterleavedIndexedMeshSlice<'a>,
+    next_indices_idx: usize,
+}
+
+impl<'a> Iterator for TryTriangleIter<'a> {
+    type Item = Result<TupleTriangle<ColoredVertex>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next_indices_idx;
+        let index_count = self.mesh.indices.len();
+
+        if idx >= index_count {
+            return None;
+        }
+
+        if idx + 3 > index_count {
+            self.next_indices_idx = index_count;
+            return Some(Err(Error::IncompleteTriangle { index_index: idx }));
+        }
+
+        self.next_indices_idx += 3;
+        let mesh = &self.mesh;
+        Some(
+            mesh.get_vertex(idx)
+                .and_then(|a| mesh.get_vertex(idx + 1).map(|b| (a, b)))
+                .and_then(|(a, b)| {
+                    mesh.get_vertex(idx + 2)
+                        .map(|c| TupleTriangle::new(a, b, c))
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::{Position, Vec3};
+
+    #[test]
+    fn test_get_vertex_short_attribute() {
+        let mut mesh = make_mesh();
+        mesh.normals = vec![0.0, 0.0, 1.0];
+
+        assert!(mesh.get_vertex(0).is_ok());
+        match mesh.get_vertex(1) {
+            Err(Error::Layout(LayoutError::AttributeLength {
+                attribute: "normal",
+                len: 3,
+                expected: 12,
+            })) => (),
+            _ => panic!("Expected attribute length error"),
+        }
+    }
+
+    #[test]
+    fn test_try_iterators() {
+        let mesh = make_mesh();
+
+        let vertices: Vec<_> = mesh.try_vertices().collect();
+        assert_eq!(vertices.len(), 8);
+        assert_eq!(
+            vertices[1].as_ref().unwrap().position(),
+            Vec3::new(1.0, 0.0, 0.0)
+        );
+        assert!(vertices[5].is_err());
+        assert_eq!(vertices.iter().filter(|v| v.is_err()).count(), 1);
+
+        let triangles: Vec<_> = mesh.try_triangles().collect();
+        assert_eq!(triangles.len(), 3);
+        assert!(triangles[0].is_ok());
+        match triangles[1] {
+            Err(Error::Layout(LayoutError::IndexOutOfRange { index: 9, .. })) => (),
+            _ => panic!("Expected index out of range error"),
+        }
+        match triangles[2] {
+            Err(Error::IncompleteTriangle { index_index: 6 }) => (),
+            _ => panic!("Expected incomplete triangle error"),
+        }
+    }
+
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+            indices: vec![0, 1, 2, 1, 3, 9, 2, 3],
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use super::deinterleaved::{gather_attribute, DeinterleavedIndexedMeshBuf};
+
+/// Values of a named per-vertex attribute channel that is not one of the
+/// standard attributes, e.g. the moisture or rust density of a vertex in a
+/// weathering simulation.
+///
+/// Like the standard attributes, the values are stored de-interleaved with one
+/// entry of `components` values per vertex of the mesh, and an attribute
+/// without values is treated as absent.
+///
+/// ```
+/// use aitios_scene::{CustomAttribute, DeinterleavedIndexedMeshBuf};
+///
+/// let mut mesh = DeinterleavedIndexedMeshBuf {
+///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+///     indices: vec![0, 1, 2],
+///     ..Default::default()
+/// };
+///
+/// mesh.custom_attributes.insert(
+///     String::from("moisture"),
+///     CustomAttribute::F32 { components: 1, values: vec![0.0, 0.5, 1.0] },
+/// );
+///
+/// assert!(mesh.check_layout().is_ok());
+/// let moisture = mesh.custom_attribute_value("moisture", 1).unwrap();
+/// assert_eq!(moisture.as_f32(), Some(&[0.5][..]));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum CustomAttribute {
+    /// Floating point values with one to four components per vertex.
+    F32 { components: usize, values: Vec<f32> },
+    /// A single unsigned integer per vertex, e.g. a material or region ID.
+    U32(Vec<u32>),
+}
+
+impl CustomAttribute {
+    /// Gets the amount of values per vertex.
+    pub fn components(&self) -> usize {
+        match *self {
+            CustomAttribute::F32 { components, .. } => components,
+            CustomAttribute::U32(_) => 1,
+        }
+    }
+
+    /// Gets the total amount of values, i.e. vertex count times components.
+    pub fn len(&self) -> usize {
+        match *self {
+            CustomAttribute::F32 { ref values, .. } => values.len(),
+            CustomAttribute::U32(ref values) => values.len(),
+        }
+    }
+
+    /// Checks whether the attribute has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the value of the vertex with the given index into the attribute values.
+    ///
+    /// # Panics
+    /// If the vertex index is out of range.
+    pub fn value(&self, vertex_idx: usize) -> CustomValue {
+        self.as_slice().value(vertex_idx)
+    }
+
+    /// Borrows the values of the attribute.
+    pub fn as_slice(&self) -> CustomAttributeSlice<'_> {
+        match *self {
+            CustomAttribute::F32 {
+                components,
+                ref values,
+            } => CustomAttributeSlice::F32 { components, values },
+            CustomAttribute::U32(ref values) => CustomAttributeSlice::U32(values),
+        }
+    }
+
+    /// Creates an attribute of the same type with the values of the given vertices.
+    pub(crate) fn gather(&self, sources: &[u32]) -> Self {
+        match *self {
+            CustomAttribute::F32 {
+                components,
+                ref values,
+            } => CustomAttribute::F32 {
+                components,
+                values: gather_attribute(values, components, sources),
+            },
+            CustomAttribute::U32(ref values) => {
+                CustomAttribute::U32(gather_attribute(values, 1, sources))
+            }
+        }
+    }
+
+    /// Creates an empty attribute with the same type and components as the given value.
+    pub(crate) fn empty_like(value: &CustomValue) -> Self {
+        match *value {
+            CustomValue::F32 { components, .. } => CustomAttribute::F32 {
+                components,
+                values: Vec::new(),
+            },
+            CustomValue::U32(_) => CustomAttribute::U32(Vec::new()),
+        }
+    }
+
+    /// Appends the value of a vertex, or zeros if the vertex has no value or a
+    /// value of another type or amount of components.
+    pub(crate) fn push(&mut self, value: Option<&CustomValue>) {
+        match (self, value) {
+            (
+                &mut CustomAttribute::F32 {
+                    components,
+                    ref mut values,
+                },
+                Some(&CustomValue::F32 {
+                    components: pushed_components,
+                    values: pushed,
+                }),
+            ) if pushed_components == components => values.extend_from_slice(&pushed[..components]),
+            (
+                &mut CustomAttribute::F32 {
+                    components,
+                    ref mut values,
+                },
+                _,
+            ) => values.extend((0..components).map(|_| 0.0)),
+            (&mut CustomAttribute::U32(ref mut values), Some(&CustomValue::U32(pushed))) => {
+                values.push(pushed)
+            }
+            (&mut CustomAttribute::U32(ref mut values), _) => values.push(0),
+        }
+    }
+}
+
+/// Borrowed values of a [`CustomAttribute`](enum.CustomAttribute.html), used
+/// by [`DeinterleavedIndexedMeshSlice`](struct.DeinterleavedIndexedMeshSlice.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CustomAttributeSlice<'a> {
+    /// Floating point values with one to four components per vertex.
+    F32 {
+        components: usize,
+        values: &'a [f32],
+    },
+    /// A single unsigned integer per vertex.
+    U32(&'a [u32]),
+}
+
+impl<'a> CustomAttributeSlice<'a> {
+    /// Gets the amount of values per vertex.
+    pub fn components(&self) -> usize {
+        match *self {
+            CustomAttributeSlice::F32 { components, .. } => components,
+            CustomAttributeSlice::U32(_) => 1,
+        }
+    }
+
+    /// Gets the total amount of values, i.e. vertex count times components.
+    pub fn len(&self) -> usize {
+        match *self {
+            CustomAttributeSlice::F32 { values, .. } => values.len(),
+            CustomAttributeSlice::U32(values) => values.len(),
+        }
+    }
+
+    /// Checks whether the attribute has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the value of the vertex with the given index into the attribute values.
+    ///
+    /// # Panics
+    /// If the vertex index is out of range.
+    pub fn value(&self, vertex_idx: usize) -> CustomValue {
+        match *self {
+            CustomAttributeSlice::F32 { components, values } => {
+                let mut padded = [0.0; 4];
+                let values = &values[vertex_idx * components..(vertex_idx + 1) * components];
+                padded[..components].copy_from_slice(values);
+                CustomValue::F32 {
+                    components,
+                    values: padded,
+                }
+            }
+            CustomAttributeSlice::U32(values) => CustomValue::U32(values[vertex_idx]),
+        }
+    }
+
+    /// Copies the borrowed values into an owned attribute.
+    pub fn to_attribute(&self) -> CustomAttribute {
+        match *self {
+            CustomAttributeSlice::F32 { components, values } => CustomAttribute::F32 {
+                components,
+                values: values.to_vec(),
+            },
+            CustomAttributeSlice::U32(values) => CustomAttribute::U32(values.to_vec()),
+        }
+    }
+}
+
+/// The value of a [`CustomAttribute`](enum.CustomAttribute.html) for a single vertex.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CustomValue {
+    /// Floating point value, only the first `components` values are used.
+    F32 {
+        components: usize,
+        values: [f32; 4],
+    },
+    U32(u32),
+}
+
+impl CustomValue {
+    /// Gets the used components of a floating point value, or `None` for integers.
+    pub fn as_f32(&self) -> Option<&[f32]> {
+        match *self {
+            CustomValue::F32 {
+                components,
+                ref values,
+            } => Some(&values[..components]),
+            CustomValue::U32(_) => None,
+        }
+    }
+
+    /// Gets an integer value, or `None` for floating point values.
+    pub fn as_u32(&self) -> Option<u32> {
+        match *self {
+            CustomValue::U32(value) => Some(value),
+            CustomValue::F32 { .. } => None,
+        }
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Gets the value of the custom attribute with the given name for the
+    /// vertex referenced by the index at the given position in the indices,
+    /// or `None` if the mesh has no such attribute or it is empty.
+    pub fn custom_attribute_value(&self, name: &str, index_index: usize) -> Option<CustomValue> {
+        let idx = self.indices[index_index] as usize;
+        self.custom_attributes
+            .get(name)
+            .filter(|attribute| !attribute.is_empty())
+            .map(|attribute| attribute.value(idx))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::{MeshVertex, NormalWeighting, Welder};
+    use transform::Transform;
+
+    #[test]
+    fn test_survives_split_and_transform() {
+        let mut mesh = make_mesh();
+        mesh.generate_normals(NormalWeighting::Flat);
+
+        // Flat normals split the shared vertices of the two triangles
+        assert_eq!(mesh.positions.len(), 6 * 3);
+        assert_eq!(
+            mesh.custom_attributes["rust"],
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5],
+            }
+        );
+        assert_eq!(
+            mesh.custom_attributes["region"],
+            CustomAttribute::U32(vec![10, 11, 12, 11, 12, 13])
+        );
+        assert!(mesh.check_layout().is_ok());
+
+        let transformed = mesh.transformed(&Transform::from_uniform_scale(2.0));
+        assert_eq!(transformed.custom_attributes, mesh.custom_attributes);
+    }
+
+    #[test]
+    fn test_survives_collect() {
+        let mesh = make_mesh();
+
+        let vertices: Vec<MeshVertex> = mesh.mesh_vertices().collect();
+        assert_eq!(
+            vertices[3].custom,
+            vec![
+                (String::from("region"), CustomValue::U32(11)),
+                (
+                    String::from("rust"),
+                    CustomValue::F32 {
+                        components: 2,
+                        values: [1.0, 1.5, 0.0, 0.0],
+                    }
+                ),
+            ]
+        );
+
+        let collected: DeinterleavedIndexedMeshBuf = vertices.into_iter().collect();
+        assert_eq!(
+            collected.custom_attributes["region"],
+            CustomAttribute::U32(vec![10, 11, 12, 11, 12, 13])
+        );
+        assert_eq!(
+            collected.custom_attribute_value("rust", 5),
+            Some(CustomValue::F32 {
+                components: 2,
+                values: [3.0, 3.5, 0.0, 0.0],
+            })
+        );
+    }
+
+    #[test]
+    fn test_collect_backfills_missing_values() {
+        let plain = MeshVertex::from(::geom::Vec3::new(0.0, 0.0, 0.0));
+        let mut wet = plain.clone();
+        wet.custom.push((
+            String::from("moisture"),
+            CustomValue::F32 {
+                components: 1,
+                values: [0.75, 0.0, 0.0, 0.0],
+            },
+        ));
+
+        let mesh: DeinterleavedIndexedMeshBuf =
+            vec![plain.clone(), wet, plain].into_iter().collect();
+
+        assert_eq!(
+            mesh.custom_attributes["moisture"],
+            CustomAttribute::F32 {
+                components: 1,
+                values: vec![0.0, 0.75, 0.0],
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_attribute_is_absent() {
+        let mut mesh = make_mesh();
+        mesh.custom_attributes
+            .insert(String::from("dirt"), CustomAttribute::U32(vec![]));
+        assert!(mesh.check_layout().is_ok());
+
+        assert_eq!(mesh.custom_attribute_value("dirt", 0), None);
+        let vertices: Vec<MeshVertex> = mesh.mesh_vertices().collect();
+        assert!(vertices.iter().all(|v| v.custom.len() == 2));
+
+        let mut welded = mesh.clone();
+        Welder::new().weld(&mut welded);
+        assert_eq!(welded.positions.len(), 4 * 3);
+        mesh.deduplicate_vertices();
+        assert_eq!(mesh.positions.len(), 4 * 3);
+        assert!(!mesh.custom_attributes.contains_key("dirt"));
+    }
+
+    /// Two triangles sharing an edge, with two custom attributes
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0],
+            indices: vec![0, 1, 2, 1, 2, 3],
+            ..Default::default()
+        };
+
+        mesh.custom_attributes.insert(
+            String::from("rust"),
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5],
+            },
+        );
+        mesh.custom_attributes.insert(
+            String::from("region"),
+            CustomAttribute::U32(vec![10, 11, 12, 13]),
+        );
+
+        mesh
+    }
+}
@@ -1,7 +1,11 @@
-use super::mesh::Mesh;
-use geom::{Normal, Position, Texcoords, Vec2, Vec3, Vertex};
+use super::custom::CustomAttribute;
+use super::mesh::{IndexedMesh, Mesh};
+use super::mutable::MeshMut;
+use super::vertex::ColoredVertex;
+use geom::{TupleTriangle, Vec2, Vec3};
+use std::collections::{BTreeMap, HashMap};
 use std::default::Default;
-use std::iter::FromIterator;
+use transform::Transform;
 
 /// An indexed triangle mesh with de-interleaved vertices, i.e.
 /// each attribute has its own vector.
@@ -9,6 +13,13 @@ use std::iter::FromIterator;
 /// Format is compatible with [tobj meshes](http://www.willusher.io/tobj/tobj/struct.Mesh.html)
 /// and can be directly created from position, normal, texcoord and index data.
 ///
+/// Only positions are required. Normals, texture coordinates, tangents and colors
+/// are optional and absent if their vector is empty, see [`layout`](#method.layout).
+/// Iterating with [`vertices`](trait.Mesh.html#tymethod.vertices) yields zeros for
+/// absent attributes, while [`mesh_vertices`](#method.mesh_vertices) yields `None`.
+/// Use [`check_layout`](#method.check_layout) to find inconsistent attribute
+/// lengths or indices before iterating over meshes from untrusted sources.
+///
 /// ```
 /// use aitios_scene::{Mesh, DeinterleavedIndexedMeshBuf};
 ///
@@ -41,14 +52,18 @@ use std::iter::FromIterator;
 ///     indices: vec![
 ///         3, 4, 5,
 ///         0, 1, 2
-///     ]
+///     ],
+///     ..Default::default()
 /// };
 ///
 /// assert_eq!(mesh.vertex_count(), 6);
 /// assert_eq!(mesh.triangle_count(), 2);
 /// ```
 ///
-/// The mesh can also be collected from an unindexed iterator over vertices:
+/// The mesh can also be collected from an unindexed iterator over vertices.
+/// Vertex types with normals and texture coordinates always add them to the
+/// mesh, so collect from [`mesh_vertices`](#method.mesh_vertices) instead of
+/// [`vertices`](trait.Mesh.html#tymethod.vertices) to copy a mesh without them:
 ///
 /// ```
 /// # extern crate aitios_geom;
@@ -74,14 +89,34 @@ use std::iter::FromIterator;
 pub struct DeinterleavedIndexedMeshBuf {
     pub positions: Vec<f32>,
     pub normals: Vec<f32>,
+    /// Texture coordinates of UV set 0, see
+    /// [`texcoord_set`](#method.texcoord_set).
     pub texcoords: Vec<f32>,
+    /// Additional texture coordinate sets with two components per vertex, where
+    /// the set at position `i` is UV set `i + 1`.
+    pub extra_texcoords: Vec<Vec<f32>>,
+    /// Optional tangents with four components per vertex, where the fourth
+    /// component is the sign of the bitangent, see
+    /// [`generate_tangents`](#method.generate_tangents). Empty if the mesh has no tangents.
+    pub tangents: Vec<f32>,
+    /// Optional RGBA vertex colors with four components per vertex, usually
+    /// in the range from zero to one. Empty if the mesh has no colors.
+    pub colors: Vec<f32>,
+    /// Named custom attributes with values for each vertex, see
+    /// [`CustomAttribute`](enum.CustomAttribute.html).
+    pub custom_attributes: BTreeMap<String, CustomAttribute>,
     pub indices: Vec<u32>,
 }
 
 impl<'a> Mesh<'a> for DeinterleavedIndexedMeshBuf {
-    type Vertex = Vertex;
+    type Vertex = ColoredVertex;
     type VertexIter = DeinterleavedIndexedMeshBufIter<'a>;
 
+    /// Iterates over the vertices in index order, with zeros for absent
+    /// normals and texture coordinates. Collecting them into a mesh adds
+    /// these zeros as attributes, use
+    /// [`mesh_vertices`](struct.DeinterleavedIndexedMeshBuf.html#method.mesh_vertices)
+    /// to keep the layout.
     fn vertices(&'a self) -> Self::VertexIter {
         self.into_iter()
     }
@@ -91,20 +126,158 @@ impl<'a> Mesh<'a> for DeinterleavedIndexedMeshBuf {
     }
 }
 
+impl<'a> IndexedMesh<'a> for DeinterleavedIndexedMeshBuf {
+    fn triangle_indices(&'a self, triangle_idx: usize) -> [u32; 3] {
+        let tri = &self.indices[triangle_idx * 3..(triangle_idx + 1) * 3];
+        [tri[0], tri[1], tri[2]]
+    }
+
+    fn triangle_at(&'a self, triangle_idx: usize) -> TupleTriangle<ColoredVertex> {
+        let first = triangle_idx * 3;
+        TupleTriangle::new(
+            self.vertex_at(first),
+            self.vertex_at(first + 1),
+            self.vertex_at(first + 2),
+        )
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    pub fn vertex_at(&self, index_index: usize) -> ColoredVertex {
+        self.as_standard_slice().vertex_at(index_index)
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Gets the RGBA color of the vertex at the given index into the index
+    /// vector, or `None` if the mesh has no colors.
+    ///
+    /// The same color is available on the vertices yielded by
+    /// [`vertices`](trait.Mesh.html#tymethod.vertices), see
+    /// [`ColoredVertex::color`](struct.ColoredVertex.html#method.color).
+    pub fn color_at(&self, index_index: usize) -> Option<[f32; 4]> {
+        self.as_standard_slice().color_at(index_index)
+    }
+}
+
 impl DeinterleavedIndexedMeshBuf {
-    pub fn vertex_at(&self, index_index: usize) -> Vertex {
-        let idx = self.indices[index_index] as usize;
+    /// Creates a copy of the mesh with the given transform applied to positions,
+    /// normals and tangents. Indices, texture coordinate sets, colors and custom
+    /// attributes are kept as they are.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let mut transformed = self.clone();
+        transformed.transform_in_place(transform);
+        transformed
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Gets the position of the vertex with the given index into the attribute vectors.
+    pub(crate) fn vertex_position(&self, vertex_idx: usize) -> Vec3 {
+        let p = &self.positions[vertex_idx * 3..(vertex_idx + 1) * 3];
+        Vec3::new(p[0], p[1], p[2])
+    }
 
-        let position = &self.positions[idx * 3..(idx + 1) * 3];
-        let normal = &self.normals[idx * 3..(idx + 1) * 3];
-        let texcoords = &self.texcoords[idx * 2..(idx + 1) * 2];
+    /// Gets the normal of the vertex with the given index into the attribute vectors.
+    pub(crate) fn vertex_normal(&self, vertex_idx: usize) -> Vec3 {
+        let n = &self.normals[vertex_idx * 3..(vertex_idx + 1) * 3];
+        Vec3::new(n[0], n[1], n[2])
+    }
+
+    /// Gets the texture coordinates of the vertex with the given index into the attribute vectors.
+    pub(crate) fn vertex_texcoords(&self, vertex_idx: usize) -> Vec2 {
+        let t = &self.texcoords[vertex_idx * 2..(vertex_idx + 1) * 2];
+        Vec2::new(t[0], t[1])
+    }
+
+    /// Gets the RGBA color of the vertex with the given index into the attribute vectors.
+    pub(crate) fn vertex_color(&self, vertex_idx: usize) -> [f32; 4] {
+        let c = &self.colors[vertex_idx * 4..(vertex_idx + 1) * 4];
+        [c[0], c[1], c[2], c[3]]
+    }
 
-        Vertex {
-            position: Vec3::new(position[0], position[1], position[2]),
-            normal: Vec3::new(normal[0], normal[1], normal[2]),
-            texcoords: Vec2::new(texcoords[0], texcoords[1]),
+    /// Creates a mesh without indices, where the vertex at position `i` has
+    /// the attributes of the vertex `sources[i]` in this mesh.
+    ///
+    /// Attributes that are empty in this mesh stay empty.
+    pub(crate) fn gather_vertices(&self, sources: &[u32]) -> Self {
+        DeinterleavedIndexedMeshBuf {
+            positions: gather_attribute(&self.positions, 3, sources),
+            normals: gather_attribute(&self.normals, 3, sources),
+            texcoords: gather_attribute(&self.texcoords, 2, sources),
+            extra_texcoords: self
+                .extra_texcoords
+                .iter()
+                .map(|set| gather_attribute(set, 2, sources))
+                .collect(),
+            tangents: gather_attribute(&self.tangents, 4, sources),
+            colors: gather_attribute(&self.colors, 4, sources),
+            custom_attributes: self
+                .custom_attributes
+                .iter()
+                .map(|(name, attribute)| (name.clone(), attribute.gather(sources)))
+                .collect(),
+            indices: Vec::new(),
         }
     }
+
+    /// Rebuilds the vertices so that each index can have its own value of an
+    /// attribute, given in `corner_values` with `stride` components per index.
+    ///
+    /// Vertices are only duplicated if they are referenced with different values,
+    /// and incomplete triangles at the end of the indices are dropped. Returns the
+    /// per-vertex values of the attribute, which the caller should store in the mesh.
+    /// Supports up to four components.
+    pub(crate) fn split_vertices_by_corner(
+        &mut self,
+        corner_values: &[f32],
+        stride: usize,
+    ) -> Vec<f32> {
+        debug_assert!(stride <= 4);
+        let corner_count = corner_values.len() / stride;
+        let mut sources = Vec::new();
+        let mut values = Vec::new();
+        let mut new_indices = HashMap::new();
+
+        let indices: Vec<u32> = self.indices[..corner_count]
+            .iter()
+            .zip(corner_values.chunks(stride))
+            .map(|(&idx, value)| {
+                let mut bits = [0_u32; 4];
+                for (b, v) in bits.iter_mut().zip(value) {
+                    *b = v.to_bits();
+                }
+                let key = (idx, bits);
+                *new_indices.entry(key).or_insert_with(|| {
+                    sources.push(idx);
+                    values.extend_from_slice(value);
+                    (sources.len() - 1) as u32
+                })
+            })
+            .collect();
+
+        let mut mesh = self.gather_vertices(&sources);
+        mesh.indices = indices;
+        *self = mesh;
+        values
+    }
+}
+
+/// Creates a vector where the entry at position `i` is the entry `sources[i]`
+/// of the given attribute with `stride` values per entry.
+///
+/// Empty attributes stay empty.
+pub(crate) fn gather_attribute<T: Copy>(attribute: &[T], stride: usize, sources: &[u32]) -> Vec<T> {
+    if attribute.is_empty() {
+        return Vec::new();
+    }
+
+    let mut gathered = Vec::with_capacity(sources.len() * stride);
+    for &src in sources {
+        let start = src as usize * stride;
+        gathered.extend_from_slice(&attribute[start..start + stride]);
+    }
+    gathered
 }
 
 impl Default for DeinterleavedIndexedMeshBuf {
@@ -113,13 +286,17 @@ impl Default for DeinterleavedIndexedMeshBuf {
             positions: vec![],
             normals: vec![],
             texcoords: vec![],
+            extra_texcoords: vec![],
+            tangents: vec![],
+            colors: vec![],
+            custom_attributes: BTreeMap::new(),
             indices: vec![],
         }
     }
 }
 
 impl<'a> IntoIterator for &'a DeinterleavedIndexedMeshBuf {
-    type Item = Vertex;
+    type Item = ColoredVertex;
     type IntoIter = DeinterleavedIndexedMeshBufIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -137,7 +314,7 @@ pub struct DeinterleavedIndexedMeshBufIter<'a> {
 }
 
 impl<'a> Iterator for DeinterleavedIndexedMeshBufIter<'a> {
-    type Item = Vertex;
+    type Item = ColoredVertex;
 
     fn next(&mut self) -> Option<Self::Item> {
         let idx = self.next_indices_idx;
@@ -152,45 +329,6 @@ impl<'a> Iterator for DeinterleavedIndexedMeshBufIter<'a> {
     }
 }
 
-impl<V> FromIterator<V> for DeinterleavedIndexedMeshBuf
-where
-    V: Position + Normal + Texcoords,
-{
-    fn from_iter<T>(iter: T) -> Self
-    where
-        T: IntoIterator<Item = V>,
-    {
-        let mut buf = DeinterleavedIndexedMeshBuf {
-            positions: Vec::new(),
-            normals: Vec::new(),
-            texcoords: Vec::new(),
-            indices: Vec::new(),
-        };
-
-        iter.into_iter().for_each(|vtx| {
-            let Vec3 {
-                x: pos_x,
-                y: pos_y,
-                z: pos_z,
-            } = vtx.position();
-            let Vec3 {
-                x: normal_x,
-                y: normal_y,
-                z: normal_z,
-            } = vtx.normal();
-            let Vec2 { x: tex_x, y: tex_y } = vtx.texcoords();
-            let idx = buf.indices.len() as u32;
-
-            buf.positions.extend(&[pos_x, pos_y, pos_z]);
-            buf.normals.extend(&[normal_x, normal_y, normal_z]);
-            buf.texcoords.extend(&[tex_x, tex_y]);
-            buf.indices.push(idx);
-        });
-
-        buf
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -203,6 +341,7 @@ mod test {
             normals: vec![],
             texcoords: vec![],
             indices: vec![],
+            ..Default::default()
         };
 
         assert!(mesh.into_iter().next().is_none());
@@ -221,6 +360,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![3, 4, 5, 0, 1, 2],
+            ..Default::default()
         };
 
         let mut iter = mesh.into_iter();
@@ -260,6 +400,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![3, 4, 5, 0, 1],
+            ..Default::default()
         };
 
         assert_eq!(mesh.vertex_count(), 5);
@@ -278,6 +419,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![0, 1, 2, 3, 4, 5],
+            ..Default::default()
         };
 
         // Collect another deinterleavedindexedmeshbuf from vertex iterator
@@ -0,0 +1,240 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use std::collections::HashMap;
+use std::u16;
+
+/// The maximum amount of vertices that can be addressed with 16-bit indices.
+const U16_VERTEX_LIMIT: usize = u16::MAX as usize + 1;
+
+/// Indices with either 16 or 32 bits per index, as expected by renderers and
+/// file formats that support both.
+///
+/// Obtained from a mesh with
+/// [`compact_indices`](struct.DeinterleavedIndexedMeshBuf.html#method.compact_indices)
+/// for exporting. Meshes themselves, including the ones created by the OBJ
+/// and PLY loaders, always store 32-bit indices and there is no mesh type with
+/// 16-bit indices, so the narrowed indices are a copy that is not updated
+/// along with the mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexBuffer {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl IndexBuffer {
+    /// Gets the amount of indices.
+    pub fn len(&self) -> usize {
+        match *self {
+            IndexBuffer::U16(ref indices) => indices.len(),
+            IndexBuffer::U32(ref indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the index at the given position, widened to 32 bits, or `None`
+    /// if out of range.
+    pub fn get(&self, index_index: usize) -> Option<u32> {
+        match *self {
+            IndexBuffer::U16(ref indices) => indices.get(index_index).map(|&idx| u32::from(idx)),
+            IndexBuffer::U32(ref indices) => indices.get(index_index).cloned(),
+        }
+    }
+
+    /// Gets the amount of bytes per index, either 2 or 4.
+    pub fn bytes_per_index(&self) -> usize {
+        match *self {
+            IndexBuffer::U16(_) => 2,
+            IndexBuffer::U32(_) => 4,
+        }
+    }
+
+    /// Converts into 32-bit indices, as stored in `DeinterleavedIndexedMeshBuf`.
+    pub fn into_u32(self) -> Vec<u32> {
+        match self {
+            IndexBuffer::U16(indices) => indices.into_iter().map(u32::from).collect(),
+            IndexBuffer::U32(indices) => indices,
+        }
+    }
+}
+
+impl From<Vec<u16>> for IndexBuffer {
+    fn from(indices: Vec<u16>) -> Self {
+        IndexBuffer::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for IndexBuffer {
+    fn from(indices: Vec<u32>) -> Self {
+        IndexBuffer::U32(indices)
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Gets the indices with 16 bits per index if every index fits into 16
+    /// bits, or with 32 bits otherwise. Unreferenced vertices do not prevent
+    /// narrowing, even if there are more than 65536 vertices.
+    ///
+    /// Use [`split_u16_addressable`](#method.split_u16_addressable) to obtain
+    /// meshes with 16-bit indices from a mesh with too many vertices.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, IndexBuffer};
+    ///
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(mesh.compact_indices(), IndexBuffer::U16(vec![0, 1, 2]));
+    /// ```
+    pub fn compact_indices(&self) -> IndexBuffer {
+        match self.indices_u16() {
+            Some(indices) => IndexBuffer::U16(indices),
+            None => IndexBuffer::U32(self.indices.clone()),
+        }
+    }
+
+    /// Gets the indices narrowed to 16 bits, or `None` if an index does not
+    /// fit into 16 bits.
+    pub fn indices_u16(&self) -> Option<Vec<u16>> {
+        if self.indices.iter().all(|&idx| idx <= u32::from(u16::MAX)) {
+            Some(self.indices.iter().map(|&idx| idx as u16).collect())
+        } else {
+            None
+        }
+    }
+
+    /// Splits the mesh into meshes with at most 65536 vertices each, so that
+    /// all of them can be rendered with 16-bit indices.
+    ///
+    /// Triangles are kept in order and never split, vertices shared between
+    /// triangles in different chunks are duplicated. Incomplete triangles at the
+    /// end of the indices are dropped. A mesh that is already small enough is
+    /// returned as a single chunk with unused vertices removed, which is empty
+    /// if the mesh has no complete triangles.
+    pub fn split_u16_addressable(&self) -> Vec<DeinterleavedIndexedMeshBuf> {
+        self.split_by_vertex_count(U16_VERTEX_LIMIT)
+    }
+
+    fn split_by_vertex_count(&self, max_vertices: usize) -> Vec<DeinterleavedIndexedMeshBuf> {
+        let mut chunks = Vec::new();
+        let mut sources = Vec::new();
+        let mut indices = Vec::new();
+        let mut new_indices = HashMap::new();
+
+        for tri in self.indices.chunks(3).filter(|tri| tri.len() == 3) {
+            let added = tri
+                .iter()
+                .filter(|idx| !new_indices.contains_key(*idx))
+                .count();
+            if sources.len() + added > max_vertices {
+                chunks.push(self.chunk(&sources, indices));
+                sources.clear();
+                indices = Vec::new();
+                new_indices.clear();
+            }
+
+            for &idx in tri {
+                let new_idx = *new_indices.entry(idx).or_insert_with(|| {
+                    sources.push(idx);
+                    (sources.len() - 1) as u32
+                });
+                indices.push(new_idx);
+            }
+        }
+
+        if !indices.is_empty() || chunks.is_empty() {
+            chunks.push(self.chunk(&sources, indices));
+        }
+
+        chunks
+    }
+
+    fn chunk(&self, sources: &[u32], indices: Vec<u32>) -> DeinterleavedIndexedMeshBuf {
+        let mut chunk = self.gather_vertices(sources);
+        chunk.indices = indices;
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::Mesh;
+
+    #[test]
+    fn test_compact_indices() {
+        let mut mesh = make_strip(4);
+        assert_eq!(mesh.compact_indices().bytes_per_index(), 2);
+
+        mesh.indices[5] = 70_000;
+        let indices = mesh.compact_indices();
+        assert_eq!(indices, IndexBuffer::U32(mesh.indices.clone()));
+        assert_eq!(indices.get(5), Some(70_000));
+        assert!(mesh.indices_u16().is_none());
+    }
+
+    #[test]
+    fn test_split() {
+        let mesh = make_strip(8);
+        let chunks = mesh.split_by_vertex_count(4);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.positions.len() / 3 <= 4));
+        assert_eq!(chunks[0].indices, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(
+            chunks.iter().map(|c| c.triangle_count()).sum::<usize>(),
+            mesh.triangle_count()
+        );
+
+        let positions: Vec<_> = chunks
+            .iter()
+            .flat_map(|c| c.vertices().map(|v| v.position))
+            .collect();
+        let expected: Vec<_> = mesh.vertices().map(|v| v.position).collect();
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn test_split_without_triangles() {
+        let mut mesh = make_strip(5);
+        mesh.indices.truncate(2);
+        let chunks = mesh.split_u16_addressable();
+
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].positions.is_empty());
+        assert!(chunks[0].indices.is_empty());
+    }
+
+    #[test]
+    fn test_split_small() {
+        let mesh = make_strip(5);
+        let chunks = mesh.split_u16_addressable();
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].positions, mesh.positions);
+        assert_eq!(chunks[0].indices, mesh.indices);
+    }
+
+    /// A strip of triangles with the given amount of vertices
+    fn make_strip(vertex_count: u32) -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: (0..vertex_count)
+                .flat_map(|i| vec![(i / 2) as f32, (i % 2) as f32, 0.0])
+                .collect(),
+            indices: (0..vertex_count - 2)
+                .flat_map(|i| {
+                    if i % 2 == 0 {
+                        vec![i, i + 1, i + 2]
+                    } else {
+                        vec![i + 1, i, i + 2]
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
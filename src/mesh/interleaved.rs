@@ -0,0 +1,376 @@
+use super::custom::CustomAttribute;
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::{AttributeOffsets, LayoutError, VertexLayout};
+use super::mesh::{IndexedMesh, Mesh};
+use super::slice::strided_vertex;
+use super::vertex::ColoredVertex;
+use geom::TupleTriangle;
+use std::collections::BTreeMap;
+use std::mem;
+use std::slice;
+
+/// An indexed triangle mesh with interleaved vertices, i.e. all attributes of
+/// a vertex are stored next to each other in a single vector, as preferred by
+/// renderers and for cache-friendly traversal.
+///
+/// Which attributes are interleaved is described by a
+/// [`VertexLayout`](struct.VertexLayout.html), and the position of each
+/// attribute within a vertex by [`offsets`](#method.offsets). Custom
+/// attributes are not interleaved, since they may not be floats, and are kept
+/// in a separate map so that conversions are lossless.
+///
+/// ```
+/// use aitios_scene::{DeinterleavedIndexedMeshBuf, InterleavedIndexedMeshBuf, Mesh};
+///
+/// let mesh = DeinterleavedIndexedMeshBuf {
+///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+///     texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+///     indices: vec![0, 1, 2],
+///     ..Default::default()
+/// };
+///
+/// let interleaved = InterleavedIndexedMeshBuf::new(&mesh).unwrap();
+/// assert_eq!(interleaved.stride(), 5);
+/// assert_eq!(&interleaved.vertex_data()[5..10], &[1.0, 0.0, 0.0, 1.0, 0.0]);
+/// assert_eq!(interleaved.vertex_bytes().len(), 15 * 4);
+/// assert_eq!(interleaved.triangle_count(), 1);
+///
+/// let deinterleaved = interleaved.to_deinterleaved();
+/// assert_eq!(deinterleaved.texcoords, mesh.texcoords);
+/// ```
+#[derive(Debug, Clone)]
+pub struct InterleavedIndexedMeshBuf {
+    layout: VertexLayout,
+    offsets: AttributeOffsets,
+    vertices: Vec<f32>,
+    /// Named custom attributes with values for each vertex, stored separately
+    /// from the interleaved attributes.
+    pub custom_attributes: BTreeMap<String, CustomAttribute>,
+    pub indices: Vec<u32>,
+}
+
+impl InterleavedIndexedMeshBuf {
+    /// Interleaves all attributes of the given mesh, or reports why the
+    /// attributes of the mesh are inconsistent.
+    ///
+    /// The conversion is lossless with one exception: an empty additional
+    /// texture coordinate set followed by a present one still takes up space
+    /// in each vertex to keep the set numbers, and is zero-filled when
+    /// converting back with [`to_deinterleaved`](#method.to_deinterleaved).
+    pub fn new(mesh: &DeinterleavedIndexedMeshBuf) -> Result<Self, Vec<LayoutError>> {
+        let layout = mesh.check_layout()?;
+        Ok(Self::interleave(mesh, layout))
+    }
+
+    /// Interleaves the attributes of the given layout, or reports why the
+    /// attributes of the mesh are inconsistent.
+    ///
+    /// Attributes of the mesh that are not in the layout are dropped, and
+    /// attributes in the layout that the mesh does not have are zero.
+    pub fn with_layout(
+        mesh: &DeinterleavedIndexedMeshBuf,
+        layout: VertexLayout,
+    ) -> Result<Self, Vec<LayoutError>> {
+        mesh.check_layout()?;
+        Ok(Self::interleave(mesh, layout))
+    }
+
+    fn interleave(mesh: &DeinterleavedIndexedMeshBuf, layout: VertexLayout) -> Self {
+        let vertex_count = mesh.positions.len() / 3;
+        let mut vertices = Vec::with_capacity(vertex_count * layout.stride());
+
+        for idx in 0..vertex_count {
+            push_attribute(&mut vertices, &mesh.positions, 3, idx);
+            if layout.normals {
+                push_attribute(&mut vertices, &mesh.normals, 3, idx);
+            }
+            if layout.texcoords {
+                push_attribute(&mut vertices, &mesh.texcoords, 2, idx);
+            }
+            for set in 0..layout.extra_texcoord_sets {
+                let values = mesh.extra_texcoords.get(set).map_or(&[][..], |s| &s[..]);
+                push_attribute(&mut vertices, values, 2, idx);
+            }
+            if layout.tangents {
+                push_attribute(&mut vertices, &mesh.tangents, 4, idx);
+            }
+            if layout.colors {
+                push_attribute(&mut vertices, &mesh.colors, 4, idx);
+            }
+        }
+
+        InterleavedIndexedMeshBuf {
+            layout,
+            offsets: layout.offsets(),
+            vertices,
+            custom_attributes: mesh.custom_attributes.clone(),
+            indices: mesh.indices.clone(),
+        }
+    }
+
+    /// Converts back into a mesh with one vector per attribute.
+    pub fn to_deinterleaved(&self) -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: self.extract(Some(self.offsets.position), 3),
+            normals: self.extract(self.offsets.normal, 3),
+            texcoords: self.extract(self.offsets.texcoords, 2),
+            extra_texcoords: self
+                .offsets
+                .extra_texcoords
+                .iter()
+                .map(|&offset| self.extract(Some(offset), 2))
+                .collect(),
+            tangents: self.extract(self.offsets.tangent, 4),
+            colors: self.extract(self.offsets.color, 4),
+            custom_attributes: self.custom_attributes.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+
+    /// Gets the attributes that are interleaved.
+    pub fn layout(&self) -> VertexLayout {
+        self.layout
+    }
+
+    /// Gets the offsets of the attributes within a vertex, in floats.
+    pub fn offsets(&self) -> &AttributeOffsets {
+        &self.offsets
+    }
+
+    /// Gets the amount of floats per vertex.
+    pub fn stride(&self) -> usize {
+        self.layout.stride()
+    }
+
+    /// Gets the interleaved attributes of all vertices.
+    pub fn vertex_data(&self) -> &[f32] {
+        &self.vertices
+    }
+
+    /// Gets the interleaved attributes of all vertices for modification.
+    pub fn vertex_data_mut(&mut self) -> &mut [f32] {
+        &mut self.vertices
+    }
+
+    /// Views the interleaved attributes as bytes in native byte order, e.g.
+    /// for uploading them to the GPU or writing them into a binary file.
+    pub fn vertex_bytes(&self) -> &[u8] {
+        f32_bytes(&self.vertices)
+    }
+
+    /// Views the indices as bytes in native byte order.
+    pub fn index_bytes(&self) -> &[u8] {
+        u32_bytes(&self.indices)
+    }
+
+    pub fn vertex_at(&self, index_index: usize) -> ColoredVertex {
+        let idx = self.indices[index_index] as usize;
+        let vertex = &self.vertices[idx * self.stride()..(idx + 1) * self.stride()];
+        strided_vertex(vertex, &self.offsets)
+    }
+
+    /// Collects the attribute at the given offset of each vertex into a
+    /// vector, which is empty if the attribute is absent.
+    fn extract(&self, offset: Option<usize>, components: usize) -> Vec<f32> {
+        match offset {
+            Some(offset) => self
+                .vertices
+                .chunks(self.stride())
+                .flat_map(|vertex| vertex[offset..offset + components].iter().cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl From<InterleavedIndexedMeshBuf> for DeinterleavedIndexedMeshBuf {
+    fn from(mesh: InterleavedIndexedMeshBuf) -> Self {
+        mesh.to_deinterleaved()
+    }
+}
+
+impl<'a> Mesh<'a> for InterleavedIndexedMeshBuf {
+    type Vertex = ColoredVertex;
+    type VertexIter = InterleavedIndexedMeshBufIter<'a>;
+
+    fn vertices(&'a self) -> Self::VertexIter {
+        InterleavedIndexedMeshBufIter {
+            mesh: self,
+            next_indices_idx: 0,
+        }
+    }
+
+    fn vertex_count(&'a self) -> usize {
+        self.indices.len()
+    }
+}
+
+impl<'a> IndexedMesh<'a> for InterleavedIndexedMeshBuf {
+    fn triangle_indices(&'a self, triangle_idx: usize) -> [u32; 3] {
+        let tri = &self.indices[triangle_idx * 3..(triangle_idx + 1) * 3];
+        [tri[0], tri[1], tri[2]]
+    }
+
+    fn triangle_at(&'a self, triangle_idx: usize) -> TupleTriangle<ColoredVertex> {
+        let first = triangle_idx * 3;
+        TupleTriangle::new(
+            self.vertex_at(first),
+            self.vertex_at(first + 1),
+            self.vertex_at(first + 2),
+        )
+    }
+}
+
+pub struct InterleavedIndexedMeshBufIter<'a> {
+    mesh: &'a InterleavedIndexedMeshBuf,
+    next_indices_idx: usize,
+}
+
+impl<'a> Iterator for InterleavedIndexedMeshBufIter<'a> {
+    type Item = ColoredVertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next_indices_idx;
+
+        if idx >= self.mesh.indices.len() {
+            None
+        } else {
+            self.next_indices_idx += 1;
+            Some(self.mesh.vertex_at(idx))
+        }
+    }
+}
+
+/// Appends the attribute of the vertex with the given index, or zeros if the
+/// attribute is absent.
+fn push_attribute(vertices: &mut Vec<f32>, values: &[f32], components: usize, idx: usize) {
+    if values.is_empty() {
+        vertices.extend((0..components).map(|_| 0.0));
+    } else {
+        vertices.extend_from_slice(&values[idx * components..(idx + 1) * components]);
+    }
+}
+
+fn f32_bytes(values: &[f32]) -> &[u8] {
+    // Sound since f32 has no padding and every byte is initialized
+    unsafe {
+        slice::from_raw_parts(
+            values.as_ptr() as *const u8,
+            values.len() * mem::size_of::<f32>(),
+        )
+    }
+}
+
+fn u32_bytes(values: &[u32]) -> &[u8] {
+    // Sound since u32 has no padding and every byte is initialized
+    unsafe {
+        slice::from_raw_parts(
+            values.as_ptr() as *const u8,
+            values.len() * mem::size_of::<u32>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::Position;
+
+    #[test]
+    fn test_roundtrip() {
+        let mesh = make_mesh();
+        let interleaved = InterleavedIndexedMeshBuf::new(&mesh).unwrap();
+
+        assert_eq!(interleaved.stride(), 3 + 3 + 2 + 2 + 4);
+        assert_eq!(interleaved.offsets().extra_texcoords, vec![8]);
+        assert_eq!(interleaved.offsets().tangent, None);
+        assert_eq!(interleaved.vertex_data().len(), 4 * interleaved.stride());
+
+        let back = interleaved.to_deinterleaved();
+        assert_eq!(back.positions, mesh.positions);
+        assert_eq!(back.normals, mesh.normals);
+        assert_eq!(back.texcoords, mesh.texcoords);
+        assert_eq!(back.extra_texcoords, mesh.extra_texcoords);
+        assert!(back.tangents.is_empty());
+        assert_eq!(back.colors, mesh.colors);
+        assert_eq!(back.custom_attributes, mesh.custom_attributes);
+        assert_eq!(back.indices, mesh.indices);
+
+        let positions: Vec<_> = interleaved.vertices().map(|v| v.position()).collect();
+        let expected: Vec<_> = mesh.vertices().map(|v| v.position()).collect();
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn test_with_layout() {
+        let mesh = make_mesh();
+        let layout = VertexLayout {
+            tangents: true,
+            ..VertexLayout::positions_only()
+        };
+        let interleaved = InterleavedIndexedMeshBuf::with_layout(&mesh, layout).unwrap();
+
+        assert_eq!(interleaved.layout(), layout);
+        assert_eq!(interleaved.stride(), 7);
+        assert_eq!(
+            &interleaved.vertex_data()[7..14],
+            &[1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+
+        let back = DeinterleavedIndexedMeshBuf::from(interleaved);
+        assert!(back.normals.is_empty());
+        assert_eq!(back.tangents, vec![0.0; 16]);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let interleaved = InterleavedIndexedMeshBuf::new(&make_mesh()).unwrap();
+
+        let bytes = interleaved.vertex_bytes();
+        assert_eq!(bytes.len(), interleaved.vertex_data().len() * 4);
+        let mut first = [0; 4];
+        first.copy_from_slice(&bytes[12..16]);
+        assert_eq!(f32::from_bits(u32::from_ne_bytes(first)), 0.0);
+        first.copy_from_slice(&bytes[16..20]);
+        assert_eq!(f32::from_bits(u32::from_ne_bytes(first)), 1.0);
+
+        assert_eq!(interleaved.index_bytes().len(), 6 * 4);
+    }
+
+    #[test]
+    fn test_empty_texcoord_set_before_present() {
+        let mut mesh = make_mesh();
+        mesh.extra_texcoords.insert(0, Vec::new());
+
+        let back = InterleavedIndexedMeshBuf::new(&mesh)
+            .unwrap()
+            .to_deinterleaved();
+        assert_eq!(back.extra_texcoords[0], vec![0.0; 8]);
+        assert_eq!(back.extra_texcoords[1], mesh.extra_texcoords[1]);
+    }
+
+    #[test]
+    fn test_inconsistent() {
+        let mut mesh = make_mesh();
+        mesh.normals.pop();
+        assert!(InterleavedIndexedMeshBuf::new(&mesh).is_err());
+    }
+
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        let mut custom_attributes = BTreeMap::new();
+        custom_attributes.insert("id".to_string(), CustomAttribute::U32(vec![4, 3, 2, 1]));
+
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+            normals: vec![0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            extra_texcoords: vec![vec![0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5]],
+            colors: vec![
+                1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+            ],
+            custom_attributes,
+            indices: vec![0, 1, 2, 2, 1, 3],
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,728 @@
+use super::custom::{CustomAttribute, CustomValue};
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::slice::DeinterleavedIndexedMeshSlice;
+use super::vertex::ColoredVertex;
+use geom::{Normal, Position, Texcoords, Vec2, Vec3, Vertex};
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+
+/// Describes which vertex attributes a mesh has.
+///
+/// Positions are always present, all other attributes are optional.
+/// In a `DeinterleavedIndexedMeshBuf`, an attribute is absent if its vector is empty.
+/// Custom attributes are not part of the layout, since they are identified by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VertexLayout {
+    pub normals: bool,
+    /// Whether texture coordinates of UV set 0 are present.
+    pub texcoords: bool,
+    /// The amount of texture coordinate sets in addition to set 0.
+    pub extra_texcoord_sets: usize,
+    pub tangents: bool,
+    pub colors: bool,
+}
+
+impl VertexLayout {
+    /// Creates a layout with positions and nothing else.
+    pub fn positions_only() -> Self {
+        VertexLayout::default()
+    }
+
+    /// Creates a layout with positions, normals and texture coordinates,
+    /// which is what `aitios_geom::Vertex` provides.
+    pub fn positions_normals_texcoords() -> Self {
+        VertexLayout {
+            normals: true,
+            texcoords: true,
+            extra_texcoord_sets: 0,
+            tangents: false,
+            colors: false,
+        }
+    }
+
+    /// Gets the union of both layouts, i.e. the attributes present in at least one of them.
+    pub fn union(&self, other: &VertexLayout) -> Self {
+        VertexLayout {
+            normals: self.normals || other.normals,
+            texcoords: self.texcoords || other.texcoords,
+            extra_texcoord_sets: self.extra_texcoord_sets.max(other.extra_texcoord_sets),
+            tangents: self.tangents || other.tangents,
+            colors: self.colors || other.colors,
+        }
+    }
+
+    /// Gets the amount of floats per vertex if all attributes of the layout
+    /// are interleaved.
+    pub fn stride(&self) -> usize {
+        3 + if self.normals { 3 } else { 0 }
+            + if self.texcoords { 2 } else { 0 }
+            + 2 * self.extra_texcoord_sets
+            + if self.tangents { 4 } else { 0 }
+            + if self.colors { 4 } else { 0 }
+    }
+
+    /// Gets the offsets of the attributes within an interleaved vertex.
+    ///
+    /// Attributes are interleaved in the order position, normal, texture
+    /// coordinates, additional texture coordinate sets, tangent and color.
+    ///
+    /// ```
+    /// use aitios_scene::VertexLayout;
+    ///
+    /// let layout = VertexLayout {
+    ///     colors: true,
+    ///     ..VertexLayout::positions_normals_texcoords()
+    /// };
+    ///
+    /// let offsets = layout.offsets();
+    /// assert_eq!(offsets.texcoords, Some(6));
+    /// assert_eq!(offsets.color, Some(8));
+    /// assert_eq!(layout.stride(), 12);
+    /// ```
+    pub fn offsets(&self) -> AttributeOffsets {
+        let mut offset = 3;
+        let mut next = |present: bool, components: usize| {
+            if present {
+                offset += components;
+                Some(offset - components)
+            } else {
+                None
+            }
+        };
+
+        let normal = next(self.normals, 3);
+        let texcoords = next(self.texcoords, 2);
+        let extra_texcoords = (0..self.extra_texcoord_sets)
+            .map(|_| next(true, 2).unwrap())
+            .collect();
+        let tangent = next(self.tangents, 4);
+        let color = next(self.colors, 4);
+
+        AttributeOffsets {
+            position: 0,
+            normal,
+            texcoords,
+            extra_texcoords,
+            tangent,
+            color,
+        }
+    }
+}
+
+/// Offsets of vertex attributes within an interleaved vertex, in floats,
+/// as obtained from [`VertexLayout::offsets`](struct.VertexLayout.html#method.offsets).
+///
+/// Absent attributes have no offset.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AttributeOffsets {
+    pub position: usize,
+    pub normal: Option<usize>,
+    pub texcoords: Option<usize>,
+    /// Offsets of the texture coordinate sets in addition to set 0.
+    pub extra_texcoords: Vec<usize>,
+    pub tangent: Option<usize>,
+    pub color: Option<usize>,
+}
+
+/// An inconsistency between the attributes and indices of a
+/// `DeinterleavedIndexedMeshBuf`, as reported by
+/// [`check_layout`](struct.DeinterleavedIndexedMeshBuf.html#method.check_layout).
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutError {
+    /// An attribute is present but does not have as many values as
+    /// the positions imply.
+    AttributeLength {
+        attribute: &'static str,
+        len: usize,
+        expected: usize,
+    },
+    /// An additional texture coordinate set is present but does not have as
+    /// many values as the positions imply.
+    TexcoordSetLength {
+        set: usize,
+        len: usize,
+        expected: usize,
+    },
+    /// A custom attribute is present but does not have as many values as
+    /// the positions imply.
+    CustomAttributeLength {
+        name: String,
+        len: usize,
+        expected: usize,
+    },
+    /// A floating point custom attribute has less than one or more than four components.
+    CustomAttributeComponents { name: String, components: usize },
+    /// The index at `index_index` references a vertex that does not exist.
+    IndexOutOfRange {
+        index_index: usize,
+        index: u32,
+        vertex_count: usize,
+    },
+    /// An attribute of a
+    /// [`StridedMeshSlice`](struct.StridedMeshSlice.html) does not fit into
+    /// the stride.
+    AttributeOffset {
+        attribute: &'static str,
+        offset: usize,
+        stride: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayoutError::AttributeLength {
+                attribute,
+                len,
+                expected,
+            } => write!(
+                f,
+                "Mesh has {} {} values, expected {}",
+                len, attribute, expected
+            ),
+            LayoutError::TexcoordSetLength { set, len, expected } => write!(
+                f,
+                "Texture coordinate set {} has {} values, expected {}",
+                set, len, expected
+            ),
+            LayoutError::CustomAttributeLength {
+                ref name,
+                len,
+                expected,
+            } => write!(
+                f,
+                "Custom attribute {} has {} values, expected {}",
+                name, len, expected
+            ),
+            LayoutError::CustomAttributeComponents {
+                ref name,
+                components,
+            } => write!(
+                f,
+                "Custom attribute {} has {} components, expected one to four",
+                name, components
+            ),
+            LayoutError::IndexOutOfRange {
+                index_index,
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "Index {} at position {} is out of range for {} vertices",
+                index, index_index, vertex_count
+            ),
+            LayoutError::AttributeOffset {
+                attribute,
+                offset,
+                stride,
+            } => write!(
+                f,
+                "Attribute {} at offset {} does not fit into stride {}",
+                attribute, offset, stride
+            ),
+        }
+    }
+}
+
+impl Error for LayoutError {}
+
+/// A vertex with optional attributes, as yielded by
+/// [`mesh_vertices`](struct.DeinterleavedIndexedMeshBuf.html#method.mesh_vertices).
+///
+/// Unlike `aitios_geom::Vertex` and [`ColoredVertex`](struct.ColoredVertex.html),
+/// absent attributes are `None` rather than zero, so collecting these vertices
+/// into a mesh preserves its layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshVertex {
+    pub position: Vec3,
+    pub normal: Option<Vec3>,
+    pub texcoords: Option<Vec2>,
+    /// Texture coordinates of the UV sets after set 0.
+    pub extra_texcoords: Vec<Vec2>,
+    /// Tangent and bitangent sign, see
+    /// [`tangent_at`](struct.DeinterleavedIndexedMeshBuf.html#method.tangent_at).
+    pub tangent: Option<(Vec3, f32)>,
+    /// RGBA color, see
+    /// [`color_at`](struct.DeinterleavedIndexedMeshBuf.html#method.color_at).
+    pub color: Option<[f32; 4]>,
+    /// Names and values of custom attributes, ordered by name.
+    pub custom: Vec<(String, CustomValue)>,
+}
+
+impl MeshVertex {
+    /// Gets the attributes present on this vertex.
+    pub fn layout(&self) -> VertexLayout {
+        VertexLayout {
+            normals: self.normal.is_some(),
+            texcoords: self.texcoords.is_some(),
+            extra_texcoord_sets: self.extra_texcoords.len(),
+            tangents: self.tangent.is_some(),
+            colors: self.color.is_some(),
+        }
+    }
+}
+
+impl Position for MeshVertex {
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+}
+
+impl From<Vec3> for MeshVertex {
+    fn from(position: Vec3) -> Self {
+        MeshVertex {
+            position,
+            normal: None,
+            texcoords: None,
+            extra_texcoords: Vec::new(),
+            tangent: None,
+            color: None,
+            custom: Vec::new(),
+        }
+    }
+}
+
+impl From<Vertex> for MeshVertex {
+    fn from(vertex: Vertex) -> Self {
+        MeshVertex {
+            position: vertex.position(),
+            normal: Some(vertex.normal()),
+            texcoords: Some(vertex.texcoords()),
+            extra_texcoords: Vec::new(),
+            tangent: None,
+            color: None,
+            custom: Vec::new(),
+        }
+    }
+}
+
+impl From<ColoredVertex> for MeshVertex {
+    fn from(vertex: ColoredVertex) -> Self {
+        MeshVertex {
+            color: vertex.color(),
+            ..MeshVertex::from(Vertex::from(vertex))
+        }
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Gets the attributes present in the mesh, without checking their lengths.
+    pub fn layout(&self) -> VertexLayout {
+        VertexLayout {
+            normals: !self.normals.is_empty(),
+            texcoords: !self.texcoords.is_empty(),
+            extra_texcoord_sets: self.extra_texcoords.len(),
+            tangents: !self.tangents.is_empty(),
+            colors: !self.colors.is_empty(),
+        }
+    }
+
+    /// Checks that all present attributes have one value per position and that
+    /// all indices reference existing vertices.
+    ///
+    /// Returns the layout of the mesh if it is consistent, or all found
+    /// inconsistencies otherwise. Meshes that pass the check can be iterated
+    /// without panicking.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, LayoutError, VertexLayout};
+    ///
+    /// let mut mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(mesh.check_layout(), Ok(VertexLayout::positions_only()));
+    ///
+    /// mesh.texcoords = vec![0.0, 0.0];
+    /// assert_eq!(
+    ///     mesh.check_layout(),
+    ///     Err(vec![LayoutError::AttributeLength { attribute: "texcoord", len: 2, expected: 6 }])
+    /// );
+    /// ```
+    pub fn check_layout(&self) -> Result<VertexLayout, Vec<LayoutError>> {
+        let vertex_count = self.positions.len() / 3;
+        let mut errors = Vec::new();
+
+        let attributes: [(&'static str, usize, usize); 5] = [
+            ("position", self.positions.len(), vertex_count * 3),
+            ("normal", self.normals.len(), vertex_count * 3),
+            ("texcoord", self.texcoords.len(), vertex_count * 2),
+            ("tangent", self.tangents.len(), vertex_count * 4),
+            ("color", self.colors.len(), vertex_count * 4),
+        ];
+
+        for &(attribute, len, expected) in &attributes {
+            if len != 0 && len != expected {
+                errors.push(LayoutError::AttributeLength {
+                    attribute,
+                    len,
+                    expected,
+                });
+            }
+        }
+
+        for (set_idx, set) in self.extra_texcoords.iter().enumerate() {
+            if !set.is_empty() && set.len() != vertex_count * 2 {
+                errors.push(LayoutError::TexcoordSetLength {
+                    set: set_idx + 1,
+                    len: set.len(),
+                    expected: vertex_count * 2,
+                });
+            }
+        }
+
+        for (name, attribute) in &self.custom_attributes {
+            let components = attribute.components();
+            if !(1..=4).contains(&components) {
+                errors.push(LayoutError::CustomAttributeComponents {
+                    name: name.clone(),
+                    components,
+                });
+            } else if !attribute.is_empty() && attribute.len() != vertex_count * components {
+                errors.push(LayoutError::CustomAttributeLength {
+                    name: name.clone(),
+                    len: attribute.len(),
+                    expected: vertex_count * components,
+                });
+            }
+        }
+
+        for (index_index, &index) in self.indices.iter().enumerate() {
+            if index as usize >= vertex_count {
+                errors.push(LayoutError::IndexOutOfRange {
+                    index_index,
+                    index,
+                    vertex_count,
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self.layout())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Gets the vertex referenced by the index at the given position in the
+    /// indices, with only the attributes present in the mesh.
+    pub fn mesh_vertex_at(&self, index_index: usize) -> MeshVertex {
+        self.as_slice().mesh_vertex_at(index_index)
+    }
+
+    /// Iterates over the vertices in index order like
+    /// [`vertices`](trait.Mesh.html#tymethod.vertices), but yields only the
+    /// attributes present in the mesh instead of zero-filling absent ones.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, MeshVertex};
+    ///
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let copy: DeinterleavedIndexedMeshBuf = mesh.mesh_vertices().collect();
+    /// assert_eq!(copy.layout(), mesh.layout());
+    /// assert!(copy.normals.is_empty());
+    /// ```
+    pub fn mesh_vertices(&self) -> MeshVertexIter<'_> {
+        MeshVertexIter {
+            mesh: self.as_slice(),
+            next_indices_idx: 0,
+        }
+    }
+}
+
+impl<'a> DeinterleavedIndexedMeshSlice<'a> {
+    /// Gets the vertex referenced by the index at the given position in the
+    /// indices, with only the attributes present in the mesh.
+    pub fn mesh_vertex_at(&self, index_index: usize) -> MeshVertex {
+        let idx = self.indices[index_index] as usize;
+        let vertex = self.vertex_at(index_index);
+
+        MeshVertex {
+            position: vertex.position,
+            normal: if self.normals.is_empty() {
+                None
+            } else {
+                Some(vertex.normal)
+            },
+            texcoords: if self.texcoords.is_empty() {
+                None
+            } else {
+                Some(vertex.texcoords)
+            },
+            // Absent additional sets are zero to keep the set numbers intact
+            extra_texcoords: (1..=self.extra_texcoords.len())
+                .map(|set| {
+                    self.texcoords_at(index_index, set)
+                        .unwrap_or_else(|| Vec2::new(0.0, 0.0))
+                })
+                .collect(),
+            tangent: self.tangent_at(index_index),
+            color: self.color_at(index_index),
+            // Empty custom attributes are absent like empty standard attributes
+            custom: self
+                .custom_attributes
+                .iter()
+                .filter(|&(_, attribute)| !attribute.is_empty())
+                .map(|(&name, attribute)| (name.to_string(), attribute.value(idx)))
+                .collect(),
+        }
+    }
+
+    /// Iterates over the vertices in index order with only the attributes
+    /// present in the mesh, see
+    /// [`DeinterleavedIndexedMeshBuf::mesh_vertices`](struct.DeinterleavedIndexedMeshBuf.html#method.mesh_vertices).
+    pub fn mesh_vertices(&self) -> MeshVertexIter<'a> {
+        MeshVertexIter {
+            mesh: self.clone(),
+            next_indices_idx: 0,
+        }
+    }
+}
+
+/// Iterates over the vertices of a `DeinterleavedIndexedMeshBuf` or
+/// `DeinterleavedIndexedMeshSlice` with optional attributes.
+#[derive(Clone)]
+pub struct MeshVertexIter<'a> {
+    mesh: DeinterleavedIndexedMeshSlice<'a>,
+    next_indices_idx: usize,
+}
+
+impl<'a> Iterator for MeshVertexIter<'a> {
+    type Item = MeshVertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next_indices_idx;
+
+        if idx >= self.mesh.indices.len() {
+            None
+        } else {
+            self.next_indices_idx += 1;
+            Some(self.mesh.mesh_vertex_at(idx))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.mesh.indices.len() - self.next_indices_idx;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Collects vertices into an unindexed mesh, converting them into
+/// `MeshVertex` first.
+///
+/// The layout of the mesh is the union of the layouts of all vertices, and
+/// the mesh has all custom attributes that occur on any of the vertices.
+/// Vertices lacking an attribute that other vertices have get zeros for it.
+///
+/// Vertex types with zeros for absent attributes, like `aitios_geom::Vertex`
+/// and the [`ColoredVertex`](struct.ColoredVertex.html) yielded by
+/// [`vertices`](trait.Mesh.html#tymethod.vertices), always have normals and
+/// texture coordinates, so the collected mesh has them too. Collect from
+/// [`mesh_vertices`](struct.DeinterleavedIndexedMeshBuf.html#method.mesh_vertices)
+/// to keep the layout of the original mesh.
+impl<V> FromIterator<V> for DeinterleavedIndexedMeshBuf
+where
+    V: Into<MeshVertex>,
+{
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = V>,
+    {
+        fn push(attribute: &mut Vec<f32>, values: Option<&[f32]>, count: usize, stride: usize) {
+            if let Some(values) = values {
+                // Backfill zeros for earlier vertices without this attribute
+                attribute.resize(count * stride, 0.0);
+                attribute.extend_from_slice(values);
+            } else if !attribute.is_empty() {
+                attribute.extend((0..stride).map(|_| 0.0));
+            }
+        }
+
+        let mut buf = DeinterleavedIndexedMeshBuf::default();
+
+        for vtx in iter {
+            let vtx = vtx.into();
+            let count = buf.indices.len();
+            let Vec3 { x, y, z } = vtx.position;
+            buf.positions.extend(&[x, y, z]);
+
+            let normal = vtx.normal.map(|n| [n.x, n.y, n.z]);
+            push(&mut buf.normals, normal.as_ref().map(|n| &n[..]), count, 3);
+
+            let texcoords = vtx.texcoords.map(|t| [t.x, t.y]);
+            push(
+                &mut buf.texcoords,
+                texcoords.as_ref().map(|t| &t[..]),
+                count,
+                2,
+            );
+
+            let set_count = buf.extra_texcoords.len().max(vtx.extra_texcoords.len());
+            buf.extra_texcoords.resize(set_count, Vec::new());
+            for (set_idx, set) in buf.extra_texcoords.iter_mut().enumerate() {
+                let texcoords = vtx.extra_texcoords.get(set_idx).map(|t| [t.x, t.y]);
+                push(set, texcoords.as_ref().map(|t| &t[..]), count, 2);
+            }
+
+            let tangent = vtx.tangent.map(|(t, w)| [t.x, t.y, t.z, w]);
+            push(
+                &mut buf.tangents,
+                tangent.as_ref().map(|t| &t[..]),
+                count,
+                4,
+            );
+
+            push(
+                &mut buf.colors,
+                vtx.color.as_ref().map(|c| &c[..]),
+                count,
+                4,
+            );
+
+            for (name, value) in &vtx.custom {
+                if !buf.custom_attributes.contains_key(name) {
+                    let mut attribute = CustomAttribute::empty_like(value);
+                    for _ in 0..count {
+                        attribute.push(None);
+                    }
+                    buf.custom_attributes.insert(name.clone(), attribute);
+                }
+            }
+
+            for (name, attribute) in &mut buf.custom_attributes {
+                let value = vtx
+                    .custom
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, value)| value);
+                attribute.push(value);
+            }
+
+            buf.indices.push(count as u32);
+        }
+
+        buf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::Mesh;
+
+    #[test]
+    fn test_positions_only_iteration() {
+        let mesh = make_positions_only();
+
+        assert_eq!(mesh.check_layout(), Ok(VertexLayout::positions_only()));
+
+        // Absent attributes are zero in the vertex type of the Mesh trait
+        let vertices: Vec<ColoredVertex> = mesh.vertices().collect();
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(vertices[1].position(), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(vertices[1].normal(), Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(vertices[1].texcoords(), Vec2::new(0.0, 0.0));
+        assert_eq!(vertices[1].color(), None);
+
+        let vertices: Vec<MeshVertex> = mesh.mesh_vertices().collect();
+        assert_eq!(vertices[1], MeshVertex::from(Vec3::new(1.0, 0.0, 0.0)));
+        assert_eq!(mesh.calculate_bounds().max, Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_collect_preserves_layout() {
+        let mut mesh = make_positions_only();
+        mesh.texcoords = vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+
+        let copy: DeinterleavedIndexedMeshBuf = mesh.mesh_vertices().collect();
+
+        assert_eq!(
+            copy.layout(),
+            VertexLayout {
+                texcoords: true,
+                ..VertexLayout::positions_only()
+            }
+        );
+        assert_eq!(copy.positions, mesh.positions);
+        assert_eq!(copy.texcoords, mesh.texcoords);
+        assert_eq!(copy.indices, mesh.indices);
+    }
+
+    #[test]
+    fn test_collect_from_vertices() {
+        let mut mesh = make_positions_only();
+        mesh.colors = vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0];
+
+        // Colors survive, but zeros for normals and texcoords become attributes
+        let copy: DeinterleavedIndexedMeshBuf = mesh.vertices().collect();
+        assert_eq!(copy.colors, mesh.colors);
+        assert_eq!(
+            copy.layout(),
+            VertexLayout {
+                colors: true,
+                ..VertexLayout::positions_normals_texcoords()
+            }
+        );
+        assert_eq!(copy.normals, vec![0.0; 9]);
+    }
+
+    #[test]
+    fn test_collect_mixed_layouts() {
+        let with_normal = MeshVertex {
+            normal: Some(Vec3::new(0.0, 0.0, 1.0)),
+            ..MeshVertex::from(Vec3::new(1.0, 2.0, 3.0))
+        };
+        let without_normal = MeshVertex::from(Vec3::new(4.0, 5.0, 6.0));
+
+        let mesh: DeinterleavedIndexedMeshBuf =
+            vec![without_normal.clone(), with_normal, without_normal]
+                .into_iter()
+                .collect();
+
+        assert!(mesh.check_layout().unwrap().normals);
+        assert_eq!(
+            mesh.normals,
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]
+        );
+        assert!(mesh.texcoords.is_empty());
+    }
+
+    #[test]
+    fn test_check_layout_reports_all_errors() {
+        let mut mesh = make_positions_only();
+        mesh.normals = vec![0.0, 0.0, 1.0];
+        mesh.indices.push(7);
+
+        let errors = mesh.check_layout().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                LayoutError::AttributeLength {
+                    attribute: "normal",
+                    len: 3,
+                    expected: 9,
+                },
+                LayoutError::IndexOutOfRange {
+                    index_index: 3,
+                    index: 7,
+                    vertex_count: 3,
+                },
+            ]
+        );
+    }
+
+    fn make_positions_only() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+}
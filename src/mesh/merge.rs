@@ -0,0 +1,415 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::LayoutError;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::iter;
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Merges the given meshes into one, e.g. to render many entities that
+    /// share a material with a single draw call.
+    ///
+    /// Vertices are appended in order and the indices of each mesh are offset
+    /// by the amount of vertices before it. If only some of the meshes have an
+    /// attribute, the others get zeros for it. The same goes for custom
+    /// attributes, where a mesh also gets zeros if its attribute with the same
+    /// name has another type or amount of components than the one of the first
+    /// mesh with that attribute. Empty custom attributes count as absent.
+    /// Incomplete triangles at the end of the indices of a mesh are dropped.
+    ///
+    /// Returns the errors of [`check_layout`](#method.check_layout) for all
+    /// meshes if any of them has attributes that do not match its positions,
+    /// since they would misalign the vertices of the meshes after it.
+    ///
+    /// The meshes are merged in model space, use
+    /// [`Entity::bake_transform`](struct.Entity.html#method.bake_transform) to
+    /// merge entities in world space.
+    ///
+    /// ```
+    /// use aitios_scene::DeinterleavedIndexedMeshBuf;
+    ///
+    /// let triangle = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    /// let colored = DeinterleavedIndexedMeshBuf {
+    ///     colors: vec![1.0; 12],
+    ///     ..triangle.clone()
+    /// };
+    ///
+    /// let merged = DeinterleavedIndexedMeshBuf::merge(&[&triangle, &colored]).unwrap();
+    /// assert_eq!(merged.indices, vec![0, 1, 2, 3, 4, 5]);
+    /// assert_eq!(&merged.colors[0..4], &[0.0, 0.0, 0.0, 0.0]);
+    /// assert_eq!(&merged.colors[12..16], &[1.0, 1.0, 1.0, 1.0]);
+    /// ```
+    pub fn merge(meshes: &[&DeinterleavedIndexedMeshBuf]) -> Result<Self, Vec<LayoutError>> {
+        let errors: Vec<LayoutError> = meshes
+            .iter()
+            .filter_map(|m| m.check_layout().err())
+            .flatten()
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let extra_texcoord_sets = meshes
+            .iter()
+            .map(|m| m.extra_texcoords.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut merged = DeinterleavedIndexedMeshBuf {
+            positions: meshes
+                .iter()
+                .flat_map(|m| m.positions.iter().cloned())
+                .collect(),
+            normals: merge_attribute(meshes, 3, |m| &m.normals),
+            texcoords: merge_attribute(meshes, 2, |m| &m.texcoords),
+            extra_texcoords: (0..extra_texcoord_sets)
+                .map(|set| {
+                    merge_attribute(meshes, 2, |m| {
+                        m.extra_texcoords.get(set).map_or(&[][..], |s| &s[..])
+                    })
+                })
+                .collect(),
+            tangents: merge_attribute(meshes, 4, |m| &m.tangents),
+            colors: merge_attribute(meshes, 4, |m| &m.colors),
+            custom_attributes: BTreeMap::new(),
+            indices: Vec::new(),
+        };
+
+        for mesh in meshes {
+            for (name, attribute) in mesh.custom_attributes.iter().filter(|(_, a)| !a.is_empty()) {
+                merged
+                    .custom_attributes
+                    .entry(name.clone())
+                    .or_insert_with(|| attribute.gather(&[]));
+            }
+        }
+        for (name, merged_attribute) in &mut merged.custom_attributes {
+            for mesh in meshes {
+                let vertex_count = mesh.positions.len() / 3;
+                match mesh.custom_attributes.get(name).filter(|a| !a.is_empty()) {
+                    Some(attribute) => (0..vertex_count)
+                        .for_each(|idx| merged_attribute.push(Some(&attribute.value(idx)))),
+                    None => (0..vertex_count).for_each(|_| merged_attribute.push(None)),
+                }
+            }
+        }
+
+        let mut vertex_offset = 0;
+        for mesh in meshes {
+            for tri in mesh.indices.chunks(3).filter(|tri| tri.len() == 3) {
+                merged
+                    .indices
+                    .extend(tri.iter().map(|&idx| idx + vertex_offset));
+            }
+            vertex_offset += (mesh.positions.len() / 3) as u32;
+        }
+
+        Ok(merged)
+    }
+
+    /// Splits the mesh into its connected components, where triangles are
+    /// connected if they share a vertex.
+    ///
+    /// Triangles that only share positions but not vertices are not
+    /// connected, use [`deduplicate_vertices`](#method.deduplicate_vertices)
+    /// or a [`Welder`](struct.Welder.html) beforehand to connect them.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn split_connected_components(&self) -> Vec<DeinterleavedIndexedMeshBuf> {
+        let mut components = DisjointSets::new(self.positions.len() / 3);
+        for tri in self.indices.chunks(3).filter(|tri| tri.len() == 3) {
+            components.union(tri[0] as usize, tri[1] as usize);
+            components.union(tri[1] as usize, tri[2] as usize);
+        }
+
+        let indices = &self.indices;
+        self.split_by_predicate(|triangle| components.find(indices[triangle * 3] as usize))
+            .into_iter()
+            .map(|(_, mesh)| mesh)
+            .collect()
+    }
+
+    /// Splits the mesh by calling the given function with the number of each
+    /// triangle and grouping the triangles with equal return values into one
+    /// mesh each.
+    ///
+    /// Groups are returned in the order their first triangle appears in the
+    /// mesh, along with the key they were obtained from. Each mesh only has the
+    /// vertices used by its triangles. Incomplete triangles at the end of the
+    /// indices are dropped.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, IndexedMesh};
+    ///
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+    ///     indices: vec![0, 1, 2, 0, 1, 3],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// // Split into triangles touching the floor and others
+    /// let parts = mesh.split_by_predicate(|triangle| {
+    ///     mesh.triangle_indices(triangle)
+    ///         .iter()
+    ///         .all(|&idx| mesh.positions[idx as usize * 3 + 2] == 0.0)
+    /// });
+    ///
+    /// assert_eq!(parts.len(), 2);
+    /// assert!(parts[0].0);
+    /// assert_eq!(parts[1].1.positions.len(), 9);
+    /// ```
+    pub fn split_by_predicate<K, F>(&self, mut key: F) -> Vec<(K, DeinterleavedIndexedMeshBuf)>
+    where
+        K: Eq + Hash + Clone,
+        F: FnMut(usize) -> K,
+    {
+        let mut groups: Vec<(K, Vec<u32>)> = Vec::new();
+        let mut group_indices = HashMap::new();
+
+        for (triangle, tri) in self
+            .indices
+            .chunks(3)
+            .enumerate()
+            .filter(|&(_, tri)| tri.len() == 3)
+        {
+            let group_key = key(triangle);
+            let group_idx = *group_indices.entry(group_key.clone()).or_insert_with(|| {
+                groups.push((group_key, Vec::new()));
+                groups.len() - 1
+            });
+            groups[group_idx].1.extend_from_slice(tri);
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, indices)| (key, self.submesh(&indices)))
+            .collect()
+    }
+
+    /// Creates a mesh with the given indices and only the vertices they
+    /// reference, keeping the vertices in their original order.
+    pub(crate) fn submesh(&self, indices: &[u32]) -> Self {
+        let mut new_indices = vec![None; self.positions.len() / 3];
+        for &idx in indices {
+            new_indices[idx as usize] = Some(0);
+        }
+        let mut sources = Vec::new();
+        for (idx, new_idx) in new_indices.iter_mut().enumerate() {
+            if new_idx.is_some() {
+                *new_idx = Some(sources.len() as u32);
+                sources.push(idx as u32);
+            }
+        }
+
+        let mut submesh = self.gather_vertices(&sources);
+        submesh.indices = indices
+            .iter()
+            .map(|&idx| new_indices[idx as usize].unwrap())
+            .collect();
+        submesh
+    }
+}
+
+/// Concatenates an attribute of all meshes, with zeros for meshes that do
+/// not have it, or returns an empty vector if no mesh has it.
+fn merge_attribute<F>(
+    meshes: &[&DeinterleavedIndexedMeshBuf],
+    components: usize,
+    attribute: F,
+) -> Vec<f32>
+where
+    F: Fn(&DeinterleavedIndexedMeshBuf) -> &[f32],
+{
+    if meshes.iter().all(|m| attribute(m).is_empty()) {
+        return Vec::new();
+    }
+
+    let mut merged = Vec::new();
+    for mesh in meshes {
+        let values = attribute(mesh);
+        if values.is_empty() {
+            let vertex_count = mesh.positions.len() / 3;
+            merged.extend(iter::repeat(0.0).take(vertex_count * components));
+        } else {
+            merged.extend_from_slice(values);
+        }
+    }
+    merged
+}
+
+/// Union-find over vertex indices.
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        DisjointSets {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parents[idx] != idx {
+            self.parents[idx] = self.parents[self.parents[idx]];
+            idx = self.parents[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::{CustomAttribute, Mesh};
+
+    #[test]
+    fn test_merge_custom_and_extra_texcoords() {
+        let mut a = make_quad();
+        a.custom_attributes
+            .insert("id".to_string(), CustomAttribute::U32(vec![1, 2, 3, 4]));
+        let mut b = make_quad();
+        b.extra_texcoords = vec![vec![0.5; 8]];
+        b.custom_attributes.insert(
+            "id".to_string(),
+            CustomAttribute::F32 {
+                components: 1,
+                values: vec![9.0; 4],
+            },
+        );
+
+        let merged = DeinterleavedIndexedMeshBuf::merge(&[&a, &b]).unwrap();
+
+        assert!(merged.check_layout().is_ok());
+        assert_eq!(merged.vertex_count(), 12);
+        assert_eq!(&merged.indices[6..], &[4, 5, 6, 6, 5, 7]);
+        assert!(merged.normals.is_empty());
+        assert_eq!(merged.extra_texcoords.len(), 1);
+        assert_eq!(&merged.extra_texcoords[0][..8], &[0.0; 8]);
+        assert_eq!(
+            merged.custom_attributes["id"],
+            CustomAttribute::U32(vec![1, 2, 3, 4, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn test_merge_custom_mismatch() {
+        let mut a = make_quad();
+        a.custom_attributes
+            .insert("dirt".to_string(), CustomAttribute::U32(vec![]));
+        a.custom_attributes.insert(
+            "rust".to_string(),
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![1.0; 8],
+            },
+        );
+        let mut b = make_quad();
+        b.custom_attributes
+            .insert("dirt".to_string(), CustomAttribute::U32(vec![5; 4]));
+        b.custom_attributes.insert(
+            "rust".to_string(),
+            CustomAttribute::F32 {
+                components: 3,
+                values: vec![2.0; 12],
+            },
+        );
+
+        let merged = DeinterleavedIndexedMeshBuf::merge(&[&a, &b]).unwrap();
+
+        assert!(merged.check_layout().is_ok());
+        assert_eq!(
+            merged.custom_attributes["dirt"],
+            CustomAttribute::U32(vec![0, 0, 0, 0, 5, 5, 5, 5])
+        );
+        let mut rust = vec![1.0; 8];
+        rust.extend(vec![0.0; 8]);
+        assert_eq!(
+            merged.custom_attributes["rust"],
+            CustomAttribute::F32 {
+                components: 2,
+                values: rust,
+            }
+        );
+
+        let merged = DeinterleavedIndexedMeshBuf::merge(&[&a]).unwrap();
+        assert!(!merged.custom_attributes.contains_key("dirt"));
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        let merged = DeinterleavedIndexedMeshBuf::merge(&[]).unwrap();
+        assert!(merged.positions.is_empty());
+        assert!(merged.indices.is_empty());
+    }
+
+    #[test]
+    fn test_merge_incomplete_and_invalid() {
+        let mut incomplete = make_quad();
+        incomplete.indices.push(0);
+        let quad = make_quad();
+
+        let merged = DeinterleavedIndexedMeshBuf::merge(&[&incomplete, &quad]).unwrap();
+        assert_eq!(merged.indices, vec![0, 1, 2, 2, 1, 3, 4, 5, 6, 6, 5, 7]);
+
+        let mut invalid = make_quad();
+        invalid.normals = vec![0.0, 0.0, 1.0];
+        match DeinterleavedIndexedMeshBuf::merge(&[&invalid, &quad]) {
+            Err(errors) => assert_eq!(
+                errors,
+                vec![LayoutError::AttributeLength {
+                    attribute: "normal",
+                    len: 3,
+                    expected: 12,
+                }]
+            ),
+            Ok(_) => panic!("Expected layout error"),
+        }
+    }
+
+    #[test]
+    fn test_split_connected_components() {
+        let quad = make_quad();
+        let mut moved = make_quad();
+        moved.positions.iter_mut().for_each(|c| *c += 10.0);
+        let mut merged = DeinterleavedIndexedMeshBuf::merge(&[&quad, &moved]).unwrap();
+        merged.texcoords = (0..16).map(|n| n as f32).collect();
+
+        let components = merged.split_connected_components();
+
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].positions, quad.positions);
+        assert_eq!(components[0].indices, quad.indices);
+        assert_eq!(components[1].positions, moved.positions);
+        assert_eq!(&components[1].texcoords[0..2], &[8.0, 9.0]);
+    }
+
+    #[test]
+    fn test_split_by_predicate() {
+        let quad = make_quad();
+        let parts = quad.split_by_predicate(|triangle| triangle % 2);
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0, 0);
+        assert_eq!(parts[0].1.indices, vec![0, 1, 2]);
+        assert_eq!(parts[1].1.indices, vec![1, 0, 2]);
+        assert_eq!(&parts[1].1.positions[6..9], &[1.0, 1.0, 0.0]);
+    }
+
+    fn make_quad() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+            indices: vec![0, 1, 2, 2, 1, 3],
+            ..Default::default()
+        }
+    }
+}
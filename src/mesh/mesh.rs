@@ -1,5 +1,11 @@
 use super::TriangleMeshIter;
-use geom::{Aabb, Position, Vec3};
+use geom::{Aabb, Position, TupleTriangle, Vec3};
+use math::triangle_area;
+
+/// Amount of vertices summed up before adding to the total when calculating
+/// the centroid. The parallel version sums up chunks of the same size, so
+/// that both add the same floating point numbers in the same order.
+pub(crate) const CENTROID_CHUNK_LEN: usize = 4096;
 
 /// Implemented by types that represent triangle meshes.
 /// They must at least provide a method for iterating over vertices.
@@ -29,15 +35,75 @@ pub trait Mesh<'a> {
 
     fn centroid(&'a self) -> Vec3 {
         let one_over_n = (self.vertex_count() as f32).recip();
-        let vertex_sum = self.vertices().map(|v| v.position()).sum::<Vec3>();
+        let mut positions = self.vertices().map(|v| v.position()).peekable();
+        let mut vertex_sum = Vec3::new(0.0, 0.0, 0.0);
+        while positions.peek().is_some() {
+            vertex_sum += positions.by_ref().take(CENTROID_CHUNK_LEN).sum::<Vec3>();
+        }
 
         one_over_n * vertex_sum
     }
+
+    /// Gets the area of each triangle, in the order of
+    /// [`triangles`](#method.triangles).
+    fn triangle_areas(&'a self) -> Vec<f32> {
+        let mut positions = self.vertices().map(|v| v.position());
+        let mut areas = Vec::with_capacity(self.triangle_count());
+        while let (Some(a), Some(b), Some(c)) =
+            (positions.next(), positions.next(), positions.next())
+        {
+            areas.push(triangle_area(a, b, c));
+        }
+        areas
+    }
+
+    /// Gets the sum of the areas of all triangles.
+    fn surface_area(&'a self) -> f32 {
+        self.triangle_areas().iter().sum()
+    }
+}
+
+/// Implemented by meshes that offer constant-time access to arbitrary
+/// triangles, e.g. for sampling or for building the leaves of a BVH.
+///
+/// Triangles are numbered in the order they are yielded by
+/// [`triangles`](trait.Mesh.html#method.triangles).
+pub trait IndexedMesh<'a>: Mesh<'a> {
+    /// Gets the indices of the vertices of the triangle with the given number.
+    ///
+    /// Panics if `triangle_idx` is not smaller than the triangle count.
+    fn triangle_indices(&'a self, triangle_idx: usize) -> [u32; 3];
+
+    /// Gets the triangle with the given number.
+    ///
+    /// Panics if `triangle_idx` is not smaller than the triangle count.
+    ///
+    /// ```
+    /// # extern crate aitios_geom;
+    /// # extern crate aitios_scene;
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, IndexedMesh};
+    /// use aitios_geom::{Position, Triangle};
+    ///
+    /// # fn main() {
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+    ///     indices: vec![0, 1, 2, 2, 1, 3],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(mesh.triangle_indices(1), [2, 1, 3]);
+    /// let triangle = mesh.triangle_at(1);
+    /// let (_, _, last) = triangle.vertices();
+    /// assert_eq!(last.position().x, 1.0);
+    /// # }
+    /// ```
+    fn triangle_at(&'a self, triangle_idx: usize) -> TupleTriangle<Self::Vertex>;
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use geom::Triangle;
     use mesh::DeinterleavedIndexedMeshBuf;
 
     #[test]
@@ -58,6 +124,40 @@ mod test {
         assert_eq!(mesh.vertices().count(), mesh.vertex_count());
     }
 
+    #[test]
+    fn test_surface_area() {
+        let mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 2.0, 2.0, 0.0],
+            indices: vec![0, 1, 2, 2, 1, 3, 0, 1],
+            ..Default::default()
+        };
+
+        assert_eq!(mesh.triangle_areas(), vec![2.0, 2.0]);
+        assert_eq!(mesh.surface_area(), 4.0);
+    }
+
+    #[test]
+    fn test_random_access() {
+        let mesh = make_test_mesh();
+
+        for (triangle_idx, triangle) in mesh.triangles().enumerate() {
+            let (a, b, c) = triangle.vertices();
+            let at = mesh.triangle_at(triangle_idx);
+            let (at_a, at_b, at_c) = at.vertices();
+            assert_eq!(at_a.position(), a.position());
+            assert_eq!(at_b.position(), b.position());
+            assert_eq!(at_c.position(), c.position());
+        }
+        assert_eq!(mesh.triangle_indices(0), [3, 4, 5]);
+        assert_eq!(mesh.triangle_indices(1), [0, 1, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_random_access_out_of_range() {
+        make_test_mesh().triangle_at(2);
+    }
+
     fn make_test_mesh() -> DeinterleavedIndexedMeshBuf {
         DeinterleavedIndexedMeshBuf {
             positions: vec![
@@ -70,6 +170,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![3, 4, 5, 0, 1, 2],
+            ..Default::default()
         }
     }
 }
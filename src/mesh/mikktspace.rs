@@ -0,0 +1,486 @@
+//! Tangent space generation following Morten S. Mikkelsen's reference
+//! implementation of [MikkTSpace](http://www.mikktspace.com/).
+//!
+//! This is an altered version of the original C implementation: it is
+//! translated to safe Rust, restricted to triangles and leaves out the parts
+//! that only affect quads or outputs this crate does not store, i.e. the
+//! bitangent and the magnitudes of the derivatives. The original source
+//! includes the notice below.
+
+// Copyright (C) 2011 by Morten S. Mikkelsen
+//
+// This software is provided 'as-is', without any express or implied
+// warranty.  In no event will the authors be held liable for any damages
+// arising from the use of this software.
+//
+// Permission is granted to anyone to use this software for any purpose,
+// including commercial applications, and to alter it and redistribute it
+// freely, subject to the following restrictions:
+//
+// 1. The origin of this software must not be misrepresented; you must not
+// claim that you wrote the original software. If you use this software
+// in a product, an acknowledgment in the product documentation would be
+// appreciated but is not required.
+//
+// 2. Altered source versions must be plainly marked as such, and must not be
+// misrepresented as being the original software.
+//
+// 3. This notice may not be removed or altered from any source distribution.
+
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use geom::Vec3;
+use math::{dot, length};
+use std::cmp;
+use std::collections::HashMap;
+
+/// Cosine of the angular threshold of 180° that the reference uses by
+/// default, tangents of triangles in a group are only kept apart if they
+/// point in exactly opposite directions.
+const THRESHOLD_COS: f32 = -1.0;
+
+/// Per-triangle state, the counterpart of `STriInfo`.
+#[derive(Debug, Clone)]
+struct TriInfo {
+    /// Index of the triangle in the mesh, triangles are reordered so that
+    /// degenerate triangles come last.
+    face: usize,
+    /// Triangle sharing the edge that starts at the corner.
+    neighbors: [Option<usize>; 3],
+    /// Group of the corner.
+    groups: [Option<usize>; 3],
+    /// Normalized derivative of the position along `u`, flipped for mirrored
+    /// texture coordinates.
+    os: Vec3,
+    /// Normalized derivative of the position along `v`, likewise flipped.
+    ot: Vec3,
+    /// Two corners of the triangle are at the same position.
+    degenerate: bool,
+    /// The texture coordinates have no area, the triangle joins any
+    /// neighboring group and does not contribute to its tangent.
+    group_with_any: bool,
+    /// The texture coordinates are not mirrored.
+    orient_preserving: bool,
+}
+
+/// Triangles around a vertex that are connected through shared edges and
+/// have the same orientation, the counterpart of `SGroup`.
+#[derive(Debug, Clone)]
+struct Group {
+    faces: Vec<usize>,
+    /// Shared corner the triangles are grouped around.
+    representative: usize,
+    orient_preserving: bool,
+}
+
+/// Generates a tangent and the sign of the bitangent for every corner of
+/// every complete triangle, four values per corner.
+///
+/// The mesh must have normals and texture coordinates for every vertex.
+/// Corners the reference leaves untouched, e.g. of triangles without texture
+/// coordinate area that have no neighbors, get the tangent `(1, 0, 0)` with
+/// sign `-1` like in the reference.
+pub(crate) fn corner_tangents(mesh: &DeinterleavedIndexedMeshBuf) -> Vec<f32> {
+    let total = mesh.indices.len() / 3;
+    let mut spaces = vec![(Vec3::new(1.0, 0.0, 0.0), false); total * 3];
+    Generator::new(mesh).generate(&mut spaces);
+
+    let mut values = Vec::with_capacity(spaces.len() * 4);
+    for &(tangent, orient_preserving) in &spaces {
+        let sign = if orient_preserving { 1.0 } else { -1.0 };
+        values.extend_from_slice(&[tangent.x, tangent.y, tangent.z, sign]);
+    }
+    values
+}
+
+struct Generator<'a> {
+    mesh: &'a DeinterleavedIndexedMeshBuf,
+    /// Three corners per triangle in the order of `tri_infos`, with every
+    /// corner replaced by the first corner with the same position, normal
+    /// and texture coordinates.
+    tri_list: Vec<usize>,
+    tri_infos: Vec<TriInfo>,
+    /// Amount of triangles that are not degenerate.
+    good: usize,
+    groups: Vec<Group>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(mesh: &'a DeinterleavedIndexedMeshBuf) -> Self {
+        Generator {
+            mesh,
+            tri_list: Vec::new(),
+            tri_infos: Vec::new(),
+            good: 0,
+            groups: Vec::new(),
+        }
+    }
+
+    fn generate(mut self, spaces: &mut [(Vec3, bool)]) {
+        let total = spaces.len() / 3;
+        self.tri_list = self.shared_corners(total * 3);
+        self.tri_infos = (0..total)
+            .map(|face| {
+                let p0 = self.position(self.tri_list[face * 3]);
+                let p1 = self.position(self.tri_list[face * 3 + 1]);
+                let p2 = self.position(self.tri_list[face * 3 + 2]);
+
+                TriInfo {
+                    face,
+                    neighbors: [None; 3],
+                    groups: [None; 3],
+                    os: Vec3::new(0.0, 0.0, 0.0),
+                    ot: Vec3::new(0.0, 0.0, 0.0),
+                    degenerate: p0 == p1 || p0 == p2 || p1 == p2,
+                    group_with_any: true,
+                    orient_preserving: false,
+                }
+            })
+            .collect();
+        self.good = self.tri_infos.iter().filter(|tri| !tri.degenerate).count();
+        if self.good == 0 {
+            return;
+        }
+
+        self.degen_prologue();
+        self.init_tri_info();
+        self.build_neighbors();
+        self.build_groups();
+        self.generate_spaces(spaces);
+        self.degen_epilogue(spaces);
+    }
+
+    /// Maps every corner to the first corner with bitwise the same position,
+    /// normal and texture coordinates, treating negative and positive zero as
+    /// equal.
+    fn shared_corners(&self, corner_count: usize) -> Vec<usize> {
+        let bits = |value: f32| if value == 0.0 { 0 } else { value.to_bits() };
+        let mut first = HashMap::new();
+
+        (0..corner_count)
+            .map(|corner| {
+                let p = self.position(corner);
+                let n = self.normal(corner);
+                let (u, v) = self.texcoords(corner);
+                let key = [
+                    bits(p.x),
+                    bits(p.y),
+                    bits(p.z),
+                    bits(n.x),
+                    bits(n.y),
+                    bits(n.z),
+                    bits(u),
+                    bits(v),
+                ];
+                *first.entry(key).or_insert(corner)
+            })
+            .collect()
+    }
+
+    /// Moves degenerate triangles to the end, swapping them with the next
+    /// good triangle in the same way as the reference.
+    fn degen_prologue(&mut self) {
+        let total = self.tri_infos.len();
+        let mut next_good = 1;
+
+        for t in 0..self.good {
+            if !self.tri_infos[t].degenerate {
+                next_good = cmp::max(next_good, t + 2);
+                continue;
+            }
+
+            while next_good < total && self.tri_infos[next_good].degenerate {
+                next_good += 1;
+            }
+            if next_good >= total {
+                break;
+            }
+
+            for i in 0..3 {
+                self.tri_list.swap(t * 3 + i, next_good * 3 + i);
+            }
+            self.tri_infos.swap(t, next_good);
+            next_good += 1;
+        }
+    }
+
+    /// Calculates the derivatives of the position along the texture
+    /// coordinates and the orientation of the good triangles.
+    fn init_tri_info(&mut self) {
+        for f in 0..self.good {
+            let corners = [
+                self.tri_list[f * 3],
+                self.tri_list[f * 3 + 1],
+                self.tri_list[f * 3 + 2],
+            ];
+            let v1 = self.position(corners[0]);
+            let d1 = self.position(corners[1]) - v1;
+            let d2 = self.position(corners[2]) - v1;
+            let t1 = self.texcoords(corners[0]);
+            let t2 = self.texcoords(corners[1]);
+            let t3 = self.texcoords(corners[2]);
+            let t21x = t2.0 - t1.0;
+            let t21y = t2.1 - t1.1;
+            let t31x = t3.0 - t1.0;
+            let t31y = t3.1 - t1.1;
+
+            let signed_area_x2 = t21x * t31y - t21y * t31x;
+            let os = d1 * t31y - d2 * t21y;
+            let ot = d1 * -t31x + d2 * t21x;
+
+            let tri = &mut self.tri_infos[f];
+            tri.orient_preserving = signed_area_x2 > 0.0;
+            if not_zero(signed_area_x2) {
+                let abs_area = signed_area_x2.abs();
+                let len_os = length(os);
+                let len_ot = length(ot);
+                let sign = if tri.orient_preserving { 1.0 } else { -1.0 };
+                if not_zero(len_os) {
+                    tri.os = os * (sign / len_os);
+                }
+                if not_zero(len_ot) {
+                    tri.ot = ot * (sign / len_ot);
+                }
+                if not_zero(len_os / abs_area) && not_zero(len_ot / abs_area) {
+                    tri.group_with_any = false;
+                }
+            }
+        }
+    }
+
+    /// Connects good triangles that share an edge in opposite directions,
+    /// pairing edges shared by more than two triangles in order.
+    fn build_neighbors(&mut self) {
+        let mut edges = Vec::with_capacity(self.good * 3);
+        for f in 0..self.good {
+            for i in 0..3 {
+                let i0 = self.tri_list[f * 3 + i];
+                let i1 = self.tri_list[f * 3 + (i + 1) % 3];
+                edges.push((cmp::min(i0, i1), cmp::max(i0, i1), f));
+            }
+        }
+        edges.sort_unstable();
+
+        for (i, &(i0, i1, f)) in edges.iter().enumerate() {
+            let (edge_a, start_a, end_a) = self.edge(f, i0, i1);
+            if self.tri_infos[f].neighbors[edge_a].is_some() {
+                continue;
+            }
+
+            let matching = edges[i + 1..]
+                .iter()
+                .take_while(|&&(j0, j1, _)| j0 == i0 && j1 == i1)
+                .map(|&(_, _, t)| (t, self.edge(t, i0, i1)))
+                .find(|&(t, (edge_b, start_b, end_b))| {
+                    start_a == end_b
+                        && end_a == start_b
+                        && self.tri_infos[t].neighbors[edge_b].is_none()
+                });
+
+            if let Some((t, (edge_b, _, _))) = matching {
+                self.tri_infos[f].neighbors[edge_a] = Some(t);
+                self.tri_infos[t].neighbors[edge_b] = Some(f);
+            }
+        }
+    }
+
+    /// Gets the number of the edge of the triangle with the given corners,
+    /// together with its corners in the winding order of the triangle.
+    fn edge(&self, f: usize, i0: usize, i1: usize) -> (usize, usize, usize) {
+        let corners = &self.tri_list[f * 3..f * 3 + 3];
+        if corners[0] == i0 || corners[0] == i1 {
+            if corners[1] == i0 || corners[1] == i1 {
+                (0, corners[0], corners[1])
+            } else {
+                (2, corners[2], corners[0])
+            }
+        } else {
+            (1, corners[1], corners[2])
+        }
+    }
+
+    /// Groups the corners of good triangles into fans around shared
+    /// corners, spreading across shared edges as long as the orientation
+    /// of the texture coordinates does not change.
+    fn build_groups(&mut self) {
+        for f in 0..self.good {
+            for i in 0..3 {
+                let tri = &self.tri_infos[f];
+                if tri.group_with_any || tri.groups[i].is_some() {
+                    continue;
+                }
+
+                let group = self.groups.len();
+                self.groups.push(Group {
+                    faces: vec![f],
+                    representative: self.tri_list[f * 3 + i],
+                    orient_preserving: tri.orient_preserving,
+                });
+                self.tri_infos[f].groups[i] = Some(group);
+
+                let left = self.tri_infos[f].neighbors[i];
+                let right = self.tri_infos[f].neighbors[(i + 2) % 3];
+                for &neighbor in [left, right].iter() {
+                    if let Some(neighbor) = neighbor {
+                        self.assign(neighbor, group);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds the triangle and its neighbors around the representative corner
+    /// of the group to the group, visiting them in the same order as the
+    /// recursion in the reference.
+    fn assign(&mut self, start: usize, group: usize) {
+        let representative = self.groups[group].representative;
+        let orient_preserving = self.groups[group].orient_preserving;
+        let mut stack = vec![start];
+
+        while let Some(f) = stack.pop() {
+            let i = self.corner_of(f, representative);
+            let tri = &mut self.tri_infos[f];
+            if tri.groups[i].is_some() {
+                continue;
+            }
+
+            if tri.group_with_any && tri.groups.iter().all(Option::is_none) {
+                tri.orient_preserving = orient_preserving;
+            }
+            if tri.orient_preserving != orient_preserving {
+                continue;
+            }
+
+            self.groups[group].faces.push(f);
+            tri.groups[i] = Some(group);
+            for &neighbor in [tri.neighbors[(i + 2) % 3], tri.neighbors[i]].iter() {
+                if let Some(neighbor) = neighbor {
+                    stack.push(neighbor);
+                }
+            }
+        }
+    }
+
+    /// Averages the tangents of the triangles in every group and writes them
+    /// to the grouped corners.
+    fn generate_spaces(&self, spaces: &mut [(Vec3, bool)]) {
+        for (g, group) in self.groups.iter().enumerate() {
+            for &f in &group.faces {
+                let tri = &self.tri_infos[f];
+                let i = (0..3)
+                    .find(|&i| tri.groups[i] == Some(g))
+                    .expect("Face in group without corner in group");
+                let n = self.normal(self.tri_list[f * 3 + i]);
+                let os = normalize_non_zero(project(tri.os, n));
+                let ot = normalize_non_zero(project(tri.ot, n));
+
+                let mut members: Vec<usize> = group
+                    .faces
+                    .iter()
+                    .cloned()
+                    .filter(|&t| {
+                        let other = &self.tri_infos[t];
+                        let cos_s = dot(os, normalize_non_zero(project(other.os, n)));
+                        let cos_t = dot(ot, normalize_non_zero(project(other.ot, n)));
+
+                        tri.group_with_any
+                            || other.group_with_any
+                            || t == f
+                            || cos_s > THRESHOLD_COS && cos_t > THRESHOLD_COS
+                    })
+                    .collect();
+                members.sort_unstable();
+
+                let tangent = self.eval_tangent(&members, group.representative);
+                spaces[tri.face * 3 + i] = (tangent, group.orient_preserving);
+            }
+        }
+    }
+
+    /// Sums up the tangents of the triangles, weighted by the angle at the
+    /// representative corner after projecting the edges onto its normal plane.
+    fn eval_tangent(&self, members: &[usize], representative: usize) -> Vec3 {
+        let mut sum = Vec3::new(0.0, 0.0, 0.0);
+
+        for &f in members {
+            if self.tri_infos[f].group_with_any {
+                continue;
+            }
+
+            let i = self.corner_of(f, representative);
+            let n = self.normal(self.tri_list[f * 3 + i]);
+            let os = normalize_non_zero(project(self.tri_infos[f].os, n));
+
+            let p0 = self.position(self.tri_list[f * 3 + (i + 2) % 3]);
+            let p1 = self.position(self.tri_list[f * 3 + i]);
+            let p2 = self.position(self.tri_list[f * 3 + (i + 1) % 3]);
+            let v1 = normalize_non_zero(project(p0 - p1, n));
+            let v2 = normalize_non_zero(project(p2 - p1, n));
+            let cos = dot(v1, v2).clamp(-1.0, 1.0);
+            let angle = f64::from(cos).acos() as f32;
+
+            sum += os * angle;
+        }
+
+        normalize_non_zero(sum)
+    }
+
+    /// Copies the tangents of degenerate triangles from the first good
+    /// triangle sharing the corner.
+    fn degen_epilogue(&self, spaces: &mut [(Vec3, bool)]) {
+        for t in self.good..self.tri_infos.len() {
+            for i in 0..3 {
+                let corner = self.tri_list[t * 3 + i];
+                let source = self.tri_list[..self.good * 3]
+                    .iter()
+                    .position(|&other| other == corner);
+
+                if let Some(j) = source {
+                    let face = self.tri_infos[j / 3].face;
+                    spaces[self.tri_infos[t].face * 3 + i] = spaces[face * 3 + j % 3];
+                }
+            }
+        }
+    }
+
+    /// Gets the corner of the triangle that is the given shared corner.
+    fn corner_of(&self, f: usize, corner: usize) -> usize {
+        (0..3)
+            .find(|&i| self.tri_list[f * 3 + i] == corner)
+            .expect("Neighboring triangle does not share the corner")
+    }
+
+    fn position(&self, corner: usize) -> Vec3 {
+        self.mesh
+            .vertex_position(self.mesh.indices[corner] as usize)
+    }
+
+    fn normal(&self, corner: usize) -> Vec3 {
+        self.mesh.vertex_normal(self.mesh.indices[corner] as usize)
+    }
+
+    fn texcoords(&self, corner: usize) -> (f32, f32) {
+        let t = self
+            .mesh
+            .vertex_texcoords(self.mesh.indices[corner] as usize);
+        (t.x, t.y)
+    }
+}
+
+fn not_zero(value: f32) -> bool {
+    value.abs() > f32::MIN_POSITIVE
+}
+
+/// Removes the component of the vector along the given unit normal.
+fn project(vector: Vec3, normal: Vec3) -> Vec3 {
+    vector - normal * dot(normal, vector)
+}
+
+/// Scales the vector to unit length unless all components are zero or
+/// subnormal, with the same rounding as the reference.
+fn normalize_non_zero(v: Vec3) -> Vec3 {
+    if not_zero(v.x) || not_zero(v.y) || not_zero(v.z) {
+        v * (1.0 / length(v))
+    } else {
+        v
+    }
+}
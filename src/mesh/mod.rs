@@ -1,7 +1,42 @@
+mod checked;
+mod custom;
 mod deinterleaved;
+mod indices;
+mod interleaved;
+mod layout;
+mod merge;
 mod mesh;
+mod mikktspace;
+mod mutable;
+mod normals;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod sample;
+mod slice;
+mod tangents;
+mod texcoords;
+mod topology;
 mod triangle;
+mod validate;
+mod vertex;
+mod weld;
 
+pub use self::checked::{TryTriangleIter, TryVertexIter};
+pub use self::custom::{CustomAttribute, CustomAttributeSlice, CustomValue};
 pub use self::deinterleaved::{DeinterleavedIndexedMeshBuf, DeinterleavedIndexedMeshBufIter};
-pub use self::mesh::Mesh;
+pub use self::indices::IndexBuffer;
+pub use self::interleaved::{InterleavedIndexedMeshBuf, InterleavedIndexedMeshBufIter};
+pub use self::layout::{AttributeOffsets, LayoutError, MeshVertex, MeshVertexIter, VertexLayout};
+pub use self::mesh::{IndexedMesh, Mesh};
+pub use self::mutable::{MeshMut, Vec2SliceMut, Vec3SliceMut};
+pub use self::normals::NormalWeighting;
+pub use self::sample::SurfaceSampler;
+pub use self::slice::{
+    DeinterleavedIndexedMeshSlice, DeinterleavedIndexedMeshSliceIter, StridedMeshSlice,
+    StridedMeshSliceIter,
+};
+pub use self::topology::{Topology, TriangleEdge};
 pub use self::triangle::TriangleMeshIter;
+pub use self::validate::ValidationReport;
+pub use self::vertex::ColoredVertex;
+pub use self::weld::{WeldTolerance, Welder};
@@ -0,0 +1,301 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use geom::{Vec2, Vec3, Vertex};
+use math::normalize;
+use transform::Transform;
+
+/// Implemented by triangle meshes that can be edited in place, e.g. to
+/// displace vertices, recompute normals or store simulation results.
+///
+/// Vertices are identified by their index into the attribute vectors rather
+/// than by their position in the index vector, so a vertex shared by multiple
+/// triangles is only visited once.
+pub trait MeshMut {
+    /// Gets a mutable view of the vertex positions.
+    fn positions_mut(&mut self) -> Vec3SliceMut<'_>;
+
+    /// Gets a mutable view of the vertex normals, or `None` if the mesh has
+    /// no normals.
+    fn normals_mut(&mut self) -> Option<Vec3SliceMut<'_>>;
+
+    /// Gets a mutable view of the texture coordinates, or `None` if the mesh
+    /// has no texture coordinates.
+    fn texcoords_mut(&mut self) -> Option<Vec2SliceMut<'_>>;
+
+    /// Calls the given closure for each vertex along with its index and
+    /// stores the changes made by the closure.
+    ///
+    /// Absent normals and texture coordinates are passed as zero and changes
+    /// to them are discarded.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, MeshMut};
+    ///
+    /// let mut mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// // Displace along the normal
+    /// mesh.for_each_vertex_mut(|_, vertex| {
+    ///     vertex.position = vertex.position + vertex.normal * 0.5;
+    /// });
+    ///
+    /// assert_eq!(&mesh.positions[0..3], &[0.0, 0.0, 0.5]);
+    /// ```
+    fn for_each_vertex_mut<F>(&mut self, f: F)
+    where
+        F: FnMut(usize, &mut Vertex);
+
+    /// Applies the transform to positions and normals of the mesh.
+    fn transform_in_place(&mut self, transform: &Transform) {
+        self.positions_mut().map(|p| transform.transform_point(p));
+        if let Some(mut normals) = self.normals_mut() {
+            normals.map(|n| transform.transform_normal(n));
+        }
+    }
+}
+
+/// Mutable view of a vertex attribute with three components per vertex,
+/// such as positions or normals.
+pub struct Vec3SliceMut<'a> {
+    values: &'a mut [f32],
+}
+
+impl<'a> Vec3SliceMut<'a> {
+    /// Creates a view of the given values, ignoring incomplete vectors at
+    /// the end.
+    pub fn new(values: &'a mut [f32]) -> Self {
+        Vec3SliceMut { values }
+    }
+
+    /// Gets the amount of vectors in the view.
+    pub fn len(&self) -> usize {
+        self.values.len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the vector at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn get(&self, idx: usize) -> Vec3 {
+        let v = &self.values[idx * 3..(idx + 1) * 3];
+        Vec3::new(v[0], v[1], v[2])
+    }
+
+    /// Replaces the vector at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn set(&mut self, idx: usize, value: Vec3) {
+        self.values[idx * 3..(idx + 1) * 3].copy_from_slice(&[value.x, value.y, value.z]);
+    }
+
+    /// Replaces every vector with the result of the given function.
+    pub fn map<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Vec3) -> Vec3,
+    {
+        for idx in 0..self.len() {
+            let value = f(self.get(idx));
+            self.set(idx, value);
+        }
+    }
+}
+
+/// Mutable view of a vertex attribute with two components per vertex,
+/// such as texture coordinates.
+pub struct Vec2SliceMut<'a> {
+    values: &'a mut [f32],
+}
+
+impl<'a> Vec2SliceMut<'a> {
+    /// Creates a view of the given values, ignoring an incomplete vector at
+    /// the end.
+    pub fn new(values: &'a mut [f32]) -> Self {
+        Vec2SliceMut { values }
+    }
+
+    /// Gets the amount of vectors in the view.
+    pub fn len(&self) -> usize {
+        self.values.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the vector at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn get(&self, idx: usize) -> Vec2 {
+        let v = &self.values[idx * 2..(idx + 1) * 2];
+        Vec2::new(v[0], v[1])
+    }
+
+    /// Replaces the vector at the given index.
+    ///
+    /// Panics if the index is out of range.
+    pub fn set(&mut self, idx: usize, value: Vec2) {
+        self.values[idx * 2..(idx + 1) * 2].copy_from_slice(&[value.x, value.y]);
+    }
+
+    /// Replaces every vector with the result of the given function.
+    pub fn map<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Vec2) -> Vec2,
+    {
+        for idx in 0..self.len() {
+            let value = f(self.get(idx));
+            self.set(idx, value);
+        }
+    }
+}
+
+impl MeshMut for DeinterleavedIndexedMeshBuf {
+    fn positions_mut(&mut self) -> Vec3SliceMut<'_> {
+        Vec3SliceMut::new(&mut self.positions)
+    }
+
+    fn normals_mut(&mut self) -> Option<Vec3SliceMut<'_>> {
+        if self.normals.is_empty() {
+            None
+        } else {
+            Some(Vec3SliceMut::new(&mut self.normals))
+        }
+    }
+
+    fn texcoords_mut(&mut self) -> Option<Vec2SliceMut<'_>> {
+        if self.texcoords.is_empty() {
+            None
+        } else {
+            Some(Vec2SliceMut::new(&mut self.texcoords))
+        }
+    }
+
+    fn for_each_vertex_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut Vertex),
+    {
+        let mut positions = Vec3SliceMut::new(&mut self.positions);
+        let mut normals = Vec3SliceMut::new(&mut self.normals);
+        let mut texcoords = Vec2SliceMut::new(&mut self.texcoords);
+
+        for idx in 0..positions.len() {
+            let mut vertex = Vertex {
+                position: positions.get(idx),
+                normal: if normals.is_empty() {
+                    Vec3::new(0.0, 0.0, 0.0)
+                } else {
+                    normals.get(idx)
+                },
+                texcoords: if texcoords.is_empty() {
+                    Vec2::new(0.0, 0.0)
+                } else {
+                    texcoords.get(idx)
+                },
+            };
+
+            f(idx, &mut vertex);
+
+            positions.set(idx, vertex.position);
+            if !normals.is_empty() {
+                normals.set(idx, vertex.normal);
+            }
+            if !texcoords.is_empty() {
+                texcoords.set(idx, vertex.texcoords);
+            }
+        }
+    }
+
+    /// Applies the transform to positions, normals and tangents of the mesh.
+    fn transform_in_place(&mut self, transform: &Transform) {
+        self.positions_mut().map(|p| transform.transform_point(p));
+        if let Some(mut normals) = self.normals_mut() {
+            normals.map(|n| transform.transform_normal(n));
+        }
+
+        // Mirroring transforms flip the handedness of the tangent frame
+        let handedness = transform.determinant().signum();
+        for t in self.tangents.chunks_mut(4) {
+            if t.len() < 4 {
+                break;
+            }
+            let tangent = normalize(transform.transform_vector(Vec3::new(t[0], t[1], t[2])));
+            t.copy_from_slice(&[tangent.x, tangent.y, tangent.z, t[3] * handedness]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_views() {
+        let mut mesh = make_mesh();
+
+        {
+            let mut positions = mesh.positions_mut();
+            assert_eq!(positions.len(), 3);
+            assert_eq!(positions.get(1), Vec3::new(1.0, 0.0, 0.0));
+            positions.set(1, Vec3::new(2.0, 0.0, 0.0));
+        }
+        assert_eq!(&mesh.positions[3..6], &[2.0, 0.0, 0.0]);
+
+        mesh.texcoords_mut()
+            .unwrap()
+            .map(|t| Vec2::new(t.x * 0.5, t.y));
+        assert_eq!(mesh.texcoords, vec![0.0, 0.0, 0.5, 0.0, 0.0, 1.0]);
+
+        mesh.normals.clear();
+        assert!(mesh.normals_mut().is_none());
+    }
+
+    #[test]
+    fn test_for_each_vertex_mut() {
+        let mut mesh = make_mesh();
+        mesh.normals.clear();
+
+        let mut visited = Vec::new();
+        mesh.for_each_vertex_mut(|idx, vertex| {
+            visited.push(idx);
+            vertex.position.z = idx as f32;
+            vertex.normal = Vec3::new(1.0, 0.0, 0.0);
+        });
+
+        assert_eq!(visited, vec![0, 1, 2]);
+        assert_eq!(
+            mesh.positions,
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 2.0]
+        );
+        assert!(mesh.normals.is_empty());
+    }
+
+    #[test]
+    fn test_transform_in_place() {
+        let transform = Transform::from_translation(Vec3::new(0.0, 0.0, 3.0))
+            * Transform::from_scale(Vec3::new(-1.0, 1.0, 1.0));
+        let mut mesh = make_mesh();
+        mesh.tangents = vec![1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0];
+
+        mesh.transform_in_place(&transform);
+
+        assert_eq!(&mesh.positions[3..6], &[-1.0, 0.0, 3.0]);
+        assert_eq!(&mesh.normals[0..3], &[0.0, 0.0, 1.0]);
+        assert_eq!(&mesh.tangents[0..4], &[-1.0, 0.0, 0.0, -1.0]);
+    }
+
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use geom::Vec3;
+use math::{cross, dot, length, normalize};
+use std::collections::HashMap;
+
+/// Determines how normals are calculated by
+/// [`generate_normals`](struct.DeinterleavedIndexedMeshBuf.html#method.generate_normals).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Every triangle gets its own vertices with the triangle normal,
+    /// resulting in a faceted look.
+    Flat,
+    /// Vertex normals are the average of adjacent triangle normals,
+    /// weighted by triangle area.
+    Area,
+    /// Vertex normals are the average of adjacent triangle normals,
+    /// weighted by the angle of the triangle at the vertex. Unlike area
+    /// weighting, this is independent of how the surface is tessellated.
+    Angle,
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Replaces the normals of the mesh with generated ones, or adds normals
+    /// to meshes that have none.
+    ///
+    /// Smooth normals are averaged over all triangles sharing a vertex
+    /// position, even if the triangles reference different vertices, e.g. at
+    /// texture seams. Flat normals split vertices so that no vertex is shared
+    /// between triangles anymore.
+    ///
+    /// Vertices of degenerate triangles that are not adjacent to other
+    /// triangles get a zero normal.
+    ///
+    /// ```
+    /// use aitios_scene::{DeinterleavedIndexedMeshBuf, NormalWeighting};
+    ///
+    /// let mut mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     normals: vec![],
+    ///     texcoords: vec![0.0; 6],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// mesh.generate_normals(NormalWeighting::Angle);
+    /// assert_eq!(mesh.normals, vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    /// ```
+    pub fn generate_normals(&mut self, weighting: NormalWeighting) {
+        let corner_normals = match weighting {
+            NormalWeighting::Flat => self.flat_corner_normals(),
+            _ => self.smooth_corner_normals(weighting, None),
+        };
+        self.apply_corner_normals(&corner_normals);
+    }
+
+    /// Like [`generate_normals`](#method.generate_normals) with smooth weighting,
+    /// but only triangles whose normals differ by at most the given crease angle
+    /// in radians are smoothed together. At sharper edges, vertices are split
+    /// so the edge stays hard.
+    ///
+    /// For `NormalWeighting::Flat`, the crease angle is ignored.
+    pub fn generate_normals_with_crease(&mut self, weighting: NormalWeighting, crease_angle: f32) {
+        let corner_normals = match weighting {
+            NormalWeighting::Flat => self.flat_corner_normals(),
+            _ => self.smooth_corner_normals(weighting, Some(crease_angle)),
+        };
+        self.apply_corner_normals(&corner_normals);
+    }
+
+    fn flat_corner_normals(&self) -> Vec<Vec3> {
+        self.indices
+            .chunks(3)
+            .filter(|tri| tri.len() == 3)
+            .flat_map(|tri| {
+                let normal = normalize(self.face_cross(tri));
+                vec![normal, normal, normal]
+            })
+            .collect()
+    }
+
+    fn smooth_corner_normals(
+        &self,
+        weighting: NormalWeighting,
+        crease_angle: Option<f32>,
+    ) -> Vec<Vec3> {
+        let corner_count = self.indices.len() - self.indices.len() % 3;
+
+        // Unit face normal and weighted face normal for each corner
+        let mut face_normals = Vec::with_capacity(corner_count);
+        let mut weighted_normals = Vec::with_capacity(corner_count);
+        for tri in self.indices[..corner_count].chunks(3) {
+            let cross = self.face_cross(tri);
+            let normal = normalize(cross);
+
+            for corner in 0..3 {
+                face_normals.push(normal);
+                weighted_normals.push(match weighting {
+                    NormalWeighting::Angle => normal * self.corner_angle(tri, corner),
+                    _ => cross,
+                });
+            }
+        }
+
+        // Group corners by position so smoothing works across seams
+        let mut corners_by_position: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (corner, &idx) in self.indices[..corner_count].iter().enumerate() {
+            let p = self.vertex_position(idx as usize);
+            corners_by_position
+                .entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+                .or_default()
+                .push(corner);
+        }
+
+        let min_cos = crease_angle.map(|angle| angle.cos());
+        let mut corner_normals = vec![Vec3::new(0.0, 0.0, 0.0); corner_count];
+
+        for corners in corners_by_position.values() {
+            match min_cos {
+                None => {
+                    let sum = corners.iter().map(|&c| weighted_normals[c]).sum::<Vec3>();
+                    let normal = normalize(sum);
+                    for &c in corners {
+                        corner_normals[c] = normal;
+                    }
+                }
+                Some(min_cos) => {
+                    for &c in corners {
+                        let sum = corners
+                            .iter()
+                            .filter(|&&other| dot(face_normals[c], face_normals[other]) >= min_cos)
+                            .map(|&other| weighted_normals[other])
+                            .sum::<Vec3>();
+                        corner_normals[c] = normalize(sum);
+                    }
+                }
+            }
+        }
+
+        corner_normals
+    }
+
+    /// Sets the given normal for each index, splitting vertices that have
+    /// more than one normal.
+    fn apply_corner_normals(&mut self, corner_normals: &[Vec3]) {
+        let corner_values: Vec<f32> = corner_normals
+            .iter()
+            .flat_map(|n| vec![n.x, n.y, n.z])
+            .collect();
+        self.normals = self.split_vertices_by_corner(&corner_values, 3);
+    }
+
+    /// Cross product of two triangle edges, with a length of twice the area
+    fn face_cross(&self, tri: &[u32]) -> Vec3 {
+        let a = self.vertex_position(tri[0] as usize);
+        let b = self.vertex_position(tri[1] as usize);
+        let c = self.vertex_position(tri[2] as usize);
+        cross(b - a, c - a)
+    }
+
+    /// Interior angle in radians at the given corner of a triangle
+    fn corner_angle(&self, tri: &[u32], corner: usize) -> f32 {
+        let p = self.vertex_position(tri[corner] as usize);
+        let to_next = self.vertex_position(tri[(corner + 1) % 3] as usize) - p;
+        let to_prev = self.vertex_position(tri[(corner + 2) % 3] as usize) - p;
+        let lengths = length(to_next) * length(to_prev);
+
+        if lengths > 0.0 {
+            (dot(to_next, to_prev) / lengths).clamp(-1.0, 1.0).acos()
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_flat_splits_vertices() {
+        let mut mesh = make_roof();
+        mesh.generate_normals(NormalWeighting::Flat);
+
+        assert_eq!(mesh.positions.len(), 6 * 3);
+        assert_eq!(mesh.texcoords.len(), 6 * 2);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_close(mesh.vertex_normal(0), normalize(Vec3::new(-1.0, 1.0, 0.0)));
+        assert_close(mesh.vertex_normal(5), normalize(Vec3::new(1.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_smooth_shares_vertices() {
+        for &weighting in &[NormalWeighting::Area, NormalWeighting::Angle] {
+            let mut mesh = make_roof();
+            mesh.generate_normals(weighting);
+
+            assert_eq!(mesh.positions.len(), 4 * 3);
+            assert_eq!(mesh.indices, vec![0, 1, 2, 2, 1, 3]);
+            // The ridge is shared and points straight up
+            assert_close(mesh.vertex_normal(1), Vec3::new(0.0, 1.0, 0.0));
+            assert_close(mesh.vertex_normal(2), Vec3::new(0.0, 1.0, 0.0));
+            assert_close(mesh.vertex_normal(0), normalize(Vec3::new(-1.0, 1.0, 0.0)));
+        }
+    }
+
+    #[test]
+    fn test_crease_angle() {
+        // The roof has a 90° angle between the faces
+        let mut mesh = make_roof();
+        mesh.generate_normals_with_crease(NormalWeighting::Angle, 0.6 * PI);
+        assert_eq!(mesh.positions.len(), 4 * 3);
+        assert_close(mesh.vertex_normal(1), Vec3::new(0.0, 1.0, 0.0));
+
+        let mut mesh = make_roof();
+        mesh.generate_normals_with_crease(NormalWeighting::Angle, 0.4 * PI);
+        // Ridge vertices are split
+        assert_eq!(mesh.positions.len(), 6 * 3);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_close(mesh.vertex_normal(1), normalize(Vec3::new(-1.0, 1.0, 0.0)));
+        assert_close(mesh.vertex_normal(3), normalize(Vec3::new(1.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_smooth_across_seams() {
+        // Same as the roof, but the right face does not share vertices
+        let mut mesh = make_roof();
+        mesh.positions.extend(&[0.0, 1.0, 0.0, 0.0, 1.0, 1.0]);
+        mesh.texcoords.extend(&[0.5, 0.5, 0.5, 0.5]);
+        mesh.indices = vec![0, 2, 1, 4, 5, 3];
+
+        mesh.generate_normals(NormalWeighting::Area);
+
+        assert_eq!(mesh.positions.len(), 6 * 3);
+        assert_close(mesh.vertex_normal(2), Vec3::new(0.0, 1.0, 0.0));
+        assert_close(mesh.vertex_normal(3), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            length(actual - expected) < 0.0001,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    /// Two triangles forming a roof with the ridge along the z axis, without normals
+    fn make_roof() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![-1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0],
+            normals: vec![],
+            texcoords: vec![0.0, 0.0, 0.5, 0.0, 0.5, 1.0, 1.0, 0.0],
+            indices: vec![0, 2, 1, 1, 2, 3],
+            ..Default::default()
+        }
+    }
+}
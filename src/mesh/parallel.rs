@@ -0,0 +1,139 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::mesh::CENTROID_CHUNK_LEN;
+use super::vertex::ColoredVertex;
+use geom::{Aabb, TupleTriangle, Vec3};
+use rayon::prelude::*;
+use std::iter;
+
+/// Parallel iteration over vertices and triangles, available with the
+/// `rayon` feature.
+impl DeinterleavedIndexedMeshBuf {
+    /// Parallel version of [`vertices`](trait.Mesh.html#tymethod.vertices),
+    /// yielding the same vertices in the same order when collected.
+    pub fn par_vertices<'a>(&'a self) -> impl IndexedParallelIterator<Item = ColoredVertex> + 'a {
+        (0..self.indices.len())
+            .into_par_iter()
+            .map(move |idx| self.vertex_at(idx))
+    }
+
+    /// Parallel version of [`triangles`](trait.Mesh.html#method.triangles).
+    ///
+    /// Like the sequential version, incomplete triangles at the end of the
+    /// index buffer are ignored.
+    pub fn par_triangles<'a>(
+        &'a self,
+    ) -> impl IndexedParallelIterator<Item = TupleTriangle<ColoredVertex>> + 'a {
+        (0..self.indices.len() / 3).into_par_iter().map(move |tri| {
+            TupleTriangle::new(
+                self.vertex_at(tri * 3),
+                self.vertex_at(tri * 3 + 1),
+                self.vertex_at(tri * 3 + 2),
+            )
+        })
+    }
+
+    /// Parallel version of [`calculate_bounds`](trait.Mesh.html#method.calculate_bounds)
+    /// with the exact same result.
+    pub fn par_calculate_bounds(&self) -> Aabb {
+        if self.indices.is_empty() {
+            return Aabb::from_points(iter::empty());
+        }
+
+        let first = self.vertex_position(self.indices[0] as usize);
+        let (min, max) = self
+            .indices
+            .par_iter()
+            .map(|&idx| self.vertex_position(idx as usize))
+            .fold_with((first, first), |(min, max), p| {
+                (component_min(min, p), component_max(max, p))
+            })
+            .reduce_with(|(min_a, max_a), (min_b, max_b)| {
+                (component_min(min_a, min_b), component_max(max_a, max_b))
+            })
+            .unwrap();
+
+        Aabb::from_points([min, max].iter().cloned())
+    }
+
+    /// Parallel version of [`centroid`](trait.Mesh.html#method.centroid)
+    /// with the exact same result, independent of the amount of threads.
+    pub fn par_centroid(&self) -> Vec3 {
+        let one_over_n = (self.indices.len() as f32).recip();
+
+        let partial_sums: Vec<Vec3> = self
+            .indices
+            .par_chunks(CENTROID_CHUNK_LEN)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|&idx| self.vertex_position(idx as usize))
+                    .sum::<Vec3>()
+            })
+            .collect();
+
+        one_over_n * partial_sums.into_iter().sum::<Vec3>()
+    }
+}
+
+fn component_min(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z))
+}
+
+fn component_max(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::{Position, Triangle};
+    use mesh::Mesh;
+
+    #[test]
+    fn test_par_iterators_match_sequential() {
+        let mesh = make_mesh(3000);
+
+        let par_positions: Vec<Vec3> = mesh.par_vertices().map(|v| v.position()).collect();
+        let positions: Vec<Vec3> = mesh.vertices().map(|v| v.position()).collect();
+        assert_eq!(par_positions, positions);
+
+        let par_triangles: Vec<_> = mesh.par_triangles().collect();
+        let triangles: Vec<_> = mesh.triangles().collect();
+        assert_eq!(par_triangles.len(), triangles.len());
+        for (par_tri, tri) in par_triangles.iter().zip(triangles.iter()) {
+            assert_eq!(par_tri.vertices().2.position(), tri.vertices().2.position());
+        }
+    }
+
+    #[test]
+    fn test_par_bounds_and_centroid() {
+        let mesh = make_mesh(10000);
+
+        let par_bounds = mesh.par_calculate_bounds();
+        let bounds = mesh.calculate_bounds();
+        assert_eq!(par_bounds.min, bounds.min);
+        assert_eq!(par_bounds.max, bounds.max);
+
+        assert_eq!(mesh.par_centroid(), mesh.centroid());
+    }
+
+    fn make_mesh(triangle_count: usize) -> DeinterleavedIndexedMeshBuf {
+        let vertex_count = triangle_count + 2;
+        let positions = (0..vertex_count)
+            .flat_map(|i| {
+                let f = i as f32;
+                vec![f.sin() * 10.0, f.cos() * 5.0, f * 0.01]
+            })
+            .collect();
+
+        DeinterleavedIndexedMeshBuf {
+            positions,
+            normals: vec![0.0; vertex_count * 3],
+            texcoords: vec![0.0; vertex_count * 2],
+            indices: (0..triangle_count as u32)
+                .flat_map(|i| vec![i, i + 1, i + 2])
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use super::mesh::IndexedMesh;
+use geom::{Normal, Position, Texcoords, Triangle, Vertex};
+use math::{normalize, triangle_area};
+use rand::Rng;
+
+/// Draws uniformly distributed random points from the surface of a mesh,
+/// e.g. to emit particles or place surfels.
+///
+/// Triangles are chosen with a probability proportional to their area, using
+/// a cumulative distribution precomputed on construction, and points are
+/// distributed uniformly within the chosen triangle. Normals and texture
+/// coordinates are interpolated from the corners of the triangle.
+///
+/// Pass a seeded RNG to obtain the same points on every run.
+///
+/// ```
+/// # extern crate aitios_scene;
+/// # extern crate rand;
+/// use aitios_scene::{DeinterleavedIndexedMeshBuf, SurfaceSampler};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// # fn main() {
+/// let mesh = DeinterleavedIndexedMeshBuf {
+///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+///     normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+///     indices: vec![0, 1, 2],
+///     ..Default::default()
+/// };
+///
+/// let sampler = SurfaceSampler::new(&mesh).unwrap();
+/// assert_eq!(sampler.total_area(), 0.5);
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let point = sampler.sample(&mut rng);
+/// assert_eq!(point.position.z, 0.0);
+/// # }
+/// ```
+pub struct SurfaceSampler<'a, M: 'a> {
+    mesh: &'a M,
+    /// The sum of the areas of each triangle and all triangles before it,
+    /// in double precision so small triangles keep their share on big meshes.
+    cumulative_areas: Vec<f64>,
+}
+
+impl<'a, M> SurfaceSampler<'a, M>
+where
+    M: IndexedMesh<'a>,
+    M::Vertex: Position + Normal + Texcoords,
+{
+    /// Prepares sampling of the given mesh, or returns `None` if the mesh has
+    /// no surface to sample from.
+    pub fn new(mesh: &'a M) -> Option<Self> {
+        let mut total = 0.0;
+        let cumulative_areas: Vec<f64> = (0..mesh.triangle_count())
+            .map(|triangle| {
+                let tri = mesh.triangle_at(triangle);
+                let (a, b, c) = tri.vertices();
+                total += f64::from(triangle_area(a.position(), b.position(), c.position()));
+                total
+            })
+            .collect();
+
+        if total > 0.0 {
+            Some(SurfaceSampler {
+                mesh,
+                cumulative_areas,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Gets the sum of the areas of all triangles.
+    pub fn total_area(&self) -> f32 {
+        self.total_area_f64() as f32
+    }
+
+    /// Gets the area of the triangle with the given number.
+    pub fn triangle_area(&self, triangle: usize) -> f32 {
+        let area = if triangle == 0 {
+            self.cumulative_areas[0]
+        } else {
+            self.cumulative_areas[triangle] - self.cumulative_areas[triangle - 1]
+        };
+        area as f32
+    }
+
+    /// Picks the number of a random triangle, with a probability proportional
+    /// to its area.
+    pub fn sample_triangle<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        // Rounding may bring the product up to the total, in which case no
+        // cumulative area would exceed it, so keep it just below
+        let total = self.total_area_f64();
+        let target = (rng.gen::<f64>() * total).min(total * (1.0 - f64::EPSILON));
+
+        // Find the first triangle whose cumulative area exceeds the target,
+        // which never is a triangle with zero area
+        let (mut low, mut high) = (0, self.cumulative_areas.len() - 1);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.cumulative_areas[mid] > target {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        low
+    }
+
+    fn total_area_f64(&self) -> f64 {
+        self.cumulative_areas[self.cumulative_areas.len() - 1]
+    }
+
+    /// Picks a random point on the surface, with normal and texture
+    /// coordinates interpolated at that point.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vertex {
+        let tri = self.mesh.triangle_at(self.sample_triangle(rng));
+        let (a, b, c) = tri.vertices();
+
+        // Uniform barycentric coordinates from two uniform random numbers
+        let sqrt_r1 = rng.gen::<f32>().sqrt();
+        let r2 = rng.gen::<f32>();
+        let (u, v, w) = (1.0 - sqrt_r1, sqrt_r1 * (1.0 - r2), sqrt_r1 * r2);
+
+        Vertex {
+            position: a.position() * u + b.position() * v + c.position() * w,
+            normal: normalize(a.normal() * u + b.normal() * v + c.normal() * w),
+            texcoords: a.texcoords() * u + b.texcoords() * v + c.texcoords() * w,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::Vec3;
+    use mesh::{DeinterleavedIndexedMeshBuf, Mesh};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_area_proportional() {
+        let mesh = make_mesh();
+        let sampler = SurfaceSampler::new(&mesh).unwrap();
+
+        assert_eq!(sampler.total_area(), mesh.surface_area());
+        assert_eq!(sampler.triangle_area(0), 0.5);
+        assert_eq!(sampler.triangle_area(1), 0.0);
+        assert_eq!(sampler.triangle_area(2), 1.5);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = [0; 3];
+        for _ in 0..10_000 {
+            counts[sampler.sample_triangle(&mut rng)] += 1;
+        }
+
+        assert_eq!(counts[1], 0);
+        assert!(counts[0] > 2_000 && counts[0] < 3_000);
+    }
+
+    #[test]
+    fn test_points_on_surface() {
+        let mesh = make_mesh();
+        let sampler = SurfaceSampler::new(&mesh).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let point = sampler.sample(&mut rng);
+            assert_eq!(point.position.z, 0.0);
+            assert!(point.position.x >= 0.0 && point.position.x <= 4.0 + 1e-5);
+            assert!(point.position.y >= 0.0 && point.position.y <= 1.0);
+            assert!((point.normal.z - 1.0).abs() < 1e-5);
+            assert!((point.texcoords.x - point.position.x / 4.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let mesh = make_mesh();
+        let sampler = SurfaceSampler::new(&mesh).unwrap();
+
+        let points = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| sampler.sample(&mut rng).position)
+                .collect::<Vec<Vec3>>()
+        };
+
+        assert_eq!(points(1), points(1));
+        assert_ne!(points(1), points(2));
+    }
+
+    #[test]
+    fn test_no_surface() {
+        let mesh = DeinterleavedIndexedMeshBuf::default();
+        assert!(SurfaceSampler::new(&mesh).is_none());
+    }
+
+    /// A triangle with area 0.5, a degenerate one, and one with area 1.5,
+    /// with texture coordinates stretched over the mesh along x
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 4.0, 0.0, 0.0, 4.0, 1.0, 0.0,
+            ],
+            normals: vec![
+                0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+            ],
+            texcoords: vec![0.0, 0.0, 0.25, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0],
+            indices: vec![0, 1, 2, 0, 0, 1, 1, 3, 4],
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use super::custom::CustomAttributeSlice;
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::{AttributeOffsets, LayoutError};
+use super::mesh::{IndexedMesh, Mesh};
+use super::vertex::ColoredVertex;
+use geom::{TupleTriangle, Vec2, Vec3};
+use std::collections::BTreeMap;
+
+/// A borrowed indexed triangle mesh with de-interleaved vertices, for data
+/// held elsewhere, such as memory-mapped files or buffers of other libraries.
+///
+/// Like in `DeinterleavedIndexedMeshBuf`, an attribute is absent if its slice
+/// is empty. Convert it into an owned mesh with [`to_buf`](#method.to_buf), or
+/// borrow an owned mesh with
+/// [`as_slice`](struct.DeinterleavedIndexedMeshBuf.html#method.as_slice).
+///
+/// ```
+/// use aitios_scene::{CustomAttributeSlice, DeinterleavedIndexedMeshSlice, Mesh};
+///
+/// let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+/// let moisture = [0.0, 0.5, 1.0];
+/// let indices = [0, 1, 2];
+///
+/// let mut mesh = DeinterleavedIndexedMeshSlice {
+///     positions: &positions,
+///     indices: &indices,
+///     ..Default::default()
+/// };
+/// mesh.custom_attributes.insert(
+///     "moisture",
+///     CustomAttributeSlice::F32 { components: 1, values: &moisture },
+/// );
+///
+/// assert_eq!(mesh.triangle_count(), 1);
+/// assert_eq!(mesh.mesh_vertex_at(1).custom[0].1.as_f32(), Some(&[0.5][..]));
+///
+/// let owned = mesh.to_buf();
+/// assert_eq!(owned.positions, positions.to_vec());
+/// assert_eq!(owned.custom_attributes["moisture"].len(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeinterleavedIndexedMeshSlice<'a> {
+    pub positions: &'a [f32],
+    pub normals: &'a [f32],
+    /// Texture coordinates of UV set 0.
+    pub texcoords: &'a [f32],
+    /// Additional texture coordinate sets with two components per vertex, where
+    /// the set at position `i` is UV set `i + 1`.
+    pub extra_texcoords: Vec<&'a [f32]>,
+    /// Optional tangents with four components per vertex.
+    pub tangents: &'a [f32],
+    /// Optional RGBA vertex colors with four components per vertex.
+    pub colors: &'a [f32],
+    /// Named custom attributes with values for each vertex.
+    pub custom_attributes: BTreeMap<&'a str, CustomAttributeSlice<'a>>,
+    pub indices: &'a [u32],
+}
+
+impl<'a> DeinterleavedIndexedMeshSlice<'a> {
+    pub fn vertex_at(&self, index_index: usize) -> ColoredVertex {
+        let idx = self.indices[index_index] as usize;
+
+        // Absent attributes are zero since the vertex type requires them
+        ColoredVertex {
+            position: vec3_at(self.positions, idx),
+            normal: if self.normals.is_empty() {
+                Vec3::new(0.0, 0.0, 0.0)
+            } else {
+                vec3_at(self.normals, idx)
+            },
+            texcoords: if self.texcoords.is_empty() {
+                Vec2::new(0.0, 0.0)
+            } else {
+                vec2_at(self.texcoords, idx)
+            },
+            color: self.color_at(index_index),
+        }
+    }
+
+    /// Gets the texture coordinates in the given UV set of the vertex at the
+    /// given index into the index vector, or `None` if the mesh has no such set.
+    pub fn texcoords_at(&self, index_index: usize, set: usize) -> Option<Vec2> {
+        let texcoords = if set == 0 {
+            self.texcoords
+        } else {
+            *self.extra_texcoords.get(set - 1)?
+        };
+
+        if texcoords.is_empty() {
+            None
+        } else {
+            Some(vec2_at(texcoords, self.indices[index_index] as usize))
+        }
+    }
+
+    /// Gets the tangent of the vertex at the given index into the index
+    /// vector, together with the sign of the bitangent, or `None` if the
+    /// mesh has no tangents.
+    pub fn tangent_at(&self, index_index: usize) -> Option<(Vec3, f32)> {
+        if self.tangents.is_empty() {
+            return None;
+        }
+
+        let idx = self.indices[index_index] as usize;
+        let t = &self.tangents[idx * 4..(idx + 1) * 4];
+        Some((Vec3::new(t[0], t[1], t[2]), t[3]))
+    }
+
+    /// Gets the RGBA color of the vertex at the given index into the index
+    /// vector, or `None` if the mesh has no colors.
+    pub fn color_at(&self, index_index: usize) -> Option<[f32; 4]> {
+        if self.colors.is_empty() {
+            return None;
+        }
+
+        let idx = self.indices[index_index] as usize;
+        let c = &self.colors[idx * 4..(idx + 1) * 4];
+        Some([c[0], c[1], c[2], c[3]])
+    }
+
+    /// Copies the borrowed data into an owned mesh.
+    pub fn to_buf(&self) -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: self.positions.to_vec(),
+            normals: self.normals.to_vec(),
+            texcoords: self.texcoords.to_vec(),
+            extra_texcoords: self
+                .extra_texcoords
+                .iter()
+                .map(|set| set.to_vec())
+                .collect(),
+            tangents: self.tangents.to_vec(),
+            colors: self.colors.to_vec(),
+            custom_attributes: self
+                .custom_attributes
+                .iter()
+                .map(|(&name, attribute)| (name.to_string(), attribute.to_attribute()))
+                .collect(),
+            indices: self.indices.to_vec(),
+        }
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Borrows the mesh as a slice with all of its attributes.
+    pub fn as_slice(&self) -> DeinterleavedIndexedMeshSlice<'_> {
+        DeinterleavedIndexedMeshSlice {
+            extra_texcoords: self.extra_texcoords.iter().map(|set| &set[..]).collect(),
+            custom_attributes: self
+                .custom_attributes
+                .iter()
+                .map(|(name, attribute)| (name.as_str(), attribute.as_slice()))
+                .collect(),
+            ..self.as_standard_slice()
+        }
+    }
+
+    /// Borrows the mesh as a slice without additional texture coordinate
+    /// sets and custom attributes, which does not allocate.
+    pub(crate) fn as_standard_slice(&self) -> DeinterleavedIndexedMeshSlice<'_> {
+        DeinterleavedIndexedMeshSlice {
+            positions: &self.positions,
+            normals: &self.normals,
+            texcoords: &self.texcoords,
+            tangents: &self.tangents,
+            colors: &self.colors,
+            indices: &self.indices,
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> From<DeinterleavedIndexedMeshSlice<'a>> for DeinterleavedIndexedMeshBuf {
+    fn from(slice: DeinterleavedIndexedMeshSlice<'a>) -> Self {
+        slice.to_buf()
+    }
+}
+
+impl<'a, 'b: 'a> Mesh<'a> for DeinterleavedIndexedMeshSlice<'b> {
+    type Vertex = ColoredVertex;
+    type VertexIter = DeinterleavedIndexedMeshSliceIter<'a, 'b>;
+
+    fn vertices(&'a self) -> Self::VertexIter {
+        DeinterleavedIndexedMeshSliceIter {
+            mesh: self,
+            next_indices_idx: 0,
+        }
+    }
+
+    fn vertex_count(&'a self) -> usize {
+        self.indices.len()
+    }
+}
+
+impl<'a, 'b: 'a> IndexedMesh<'a> for DeinterleavedIndexedMeshSlice<'b> {
+    fn triangle_indices(&'a self, triangle_idx: usize) -> [u32; 3] {
+        let tri = &self.indices[triangle_idx * 3..(triangle_idx + 1) * 3];
+        [tri[0], tri[1], tri[2]]
+    }
+
+    fn triangle_at(&'a self, triangle_idx: usize) -> TupleTriangle<ColoredVertex> {
+        let first = triangle_idx * 3;
+        TupleTriangle::new(
+            self.vertex_at(first),
+            self.vertex_at(first + 1),
+            self.vertex_at(first + 2),
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DeinterleavedIndexedMeshSliceIter<'a, 'b: 'a> {
+    mesh: &'a DeinterleavedIndexedMeshSlice<'b>,
+    next_indices_idx: usize,
+}
+
+impl<'a, 'b: 'a> Iterator for DeinterleavedIndexedMeshSliceIter<'a, 'b> {
+    type Item = ColoredVertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next_indices_idx;
+
+        if idx >= self.mesh.indices.len() {
+            None
+        } else {
+            self.next_indices_idx += 1;
+            Some(self.mesh.vertex_at(idx))
+        }
+    }
+}
+
+/// A borrowed indexed triangle mesh over interleaved vertex data with an
+/// arbitrary stride, e.g. a vertex buffer of another library that contains
+/// attributes this crate does not know about.
+///
+/// Stride and offsets are given in floats. Only position, normal, texture
+/// coordinates and color are yielded by [`vertices`](trait.Mesh.html#tymethod.vertices),
+/// but [`to_buf`](#method.to_buf) copies all attributes with an offset.
+///
+/// Use [`new`](#method.new) to check that the attributes fit into the stride
+/// and that the indices are in range, otherwise accessing vertices panics.
+///
+/// ```
+/// use aitios_scene::{AttributeOffsets, Mesh, StridedMeshSlice};
+///
+/// // Position and a temperature that is not part of the offsets
+/// let data = [0.0, 0.0, 0.0, 20.5, 1.0, 0.0, 0.0, 21.0, 0.0, 1.0, 0.0, 19.0];
+///
+/// let mesh = StridedMeshSlice::new(&data, 4, AttributeOffsets::default(), &[0, 1, 2]).unwrap();
+///
+/// assert_eq!(mesh.to_buf().positions[3..6], [1.0, 0.0, 0.0]);
+/// assert_eq!(mesh.vertices().count(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StridedMeshSlice<'a> {
+    pub data: &'a [f32],
+    /// The amount of floats from the start of one vertex to the next.
+    pub stride: usize,
+    pub offsets: AttributeOffsets,
+    pub indices: &'a [u32],
+}
+
+impl<'a> StridedMeshSlice<'a> {
+    /// Creates a strided slice, or reports attributes that do not fit into
+    /// the stride and indices that reference vertices beyond the data.
+    pub fn new(
+        data: &'a [f32],
+        stride: usize,
+        offsets: AttributeOffsets,
+        indices: &'a [u32],
+    ) -> Result<Self, Vec<LayoutError>> {
+        let slice = StridedMeshSlice {
+            data,
+            stride,
+            offsets,
+            indices,
+        };
+
+        let mut errors: Vec<LayoutError> = slice
+            .attribute_ranges()
+            .into_iter()
+            .filter(|&(_, offset, components)| offset + components > stride)
+            .map(|(attribute, offset, _)| LayoutError::AttributeOffset {
+                attribute,
+                offset,
+                stride,
+            })
+            .collect();
+
+        if errors.is_empty() {
+            let vertex_count = slice.data_vertex_count();
+            errors.extend(
+                indices
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &index)| index as usize >= vertex_count)
+                    .map(|(index_index, &index)| LayoutError::IndexOutOfRange {
+                        index_index,
+                        index,
+                        vertex_count,
+                    }),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(slice)
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn vertex_at(&self, index_index: usize) -> ColoredVertex {
+        let vertex = &self.data[self.indices[index_index] as usize * self.stride..];
+        strided_vertex(vertex, &self.offsets)
+    }
+
+    /// Copies all attributes with an offset into an owned mesh.
+    pub fn to_buf(&self) -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: self.extract(Some(self.offsets.position), 3),
+            normals: self.extract(self.offsets.normal, 3),
+            texcoords: self.extract(self.offsets.texcoords, 2),
+            extra_texcoords: self
+                .offsets
+                .extra_texcoords
+                .iter()
+                .map(|&offset| self.extract(Some(offset), 2))
+                .collect(),
+            tangents: self.extract(self.offsets.tangent, 4),
+            colors: self.extract(self.offsets.color, 4),
+            indices: self.indices.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// Gets name, offset and component count of every attribute with an offset.
+    fn attribute_ranges(&self) -> Vec<(&'static str, usize, usize)> {
+        let offsets = &self.offsets;
+        let mut ranges = vec![("position", offsets.position, 3)];
+        ranges.extend(offsets.normal.map(|o| ("normal", o, 3)));
+        ranges.extend(offsets.texcoords.map(|o| ("texcoord", o, 2)));
+        ranges.extend(offsets.extra_texcoords.iter().map(|&o| ("texcoord", o, 2)));
+        ranges.extend(offsets.tangent.map(|o| ("tangent", o, 4)));
+        ranges.extend(offsets.color.map(|o| ("color", o, 4)));
+        ranges
+    }
+
+    /// Gets the amount of vertices in the data. The last vertex may not be
+    /// padded to the full stride, but must hold all attributes.
+    fn data_vertex_count(&self) -> usize {
+        let vertex_len = self
+            .attribute_ranges()
+            .into_iter()
+            .map(|(_, offset, components)| offset + components)
+            .max()
+            .unwrap_or(0);
+        let full = self.data.len() / self.stride;
+        if self.data.len() % self.stride >= vertex_len {
+            full + 1
+        } else {
+            full
+        }
+    }
+
+    fn extract(&self, offset: Option<usize>, components: usize) -> Vec<f32> {
+        match offset {
+            Some(offset) => self
+                .data
+                .chunks(self.stride)
+                .take(self.data_vertex_count())
+                .flat_map(|vertex| vertex[offset..offset + components].iter().cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<'a, 'b: 'a> Mesh<'a> for StridedMeshSlice<'b> {
+    type Vertex = ColoredVertex;
+    type VertexIter = StridedMeshSliceIter<'a, 'b>;
+
+    fn vertices(&'a self) -> Self::VertexIter {
+        StridedMeshSliceIter {
+            mesh: self,
+            next_indices_idx: 0,
+        }
+    }
+
+    fn vertex_count(&'a self) -> usize {
+        self.indices.len()
+    }
+}
+
+pub struct StridedMeshSliceIter<'a, 'b: 'a> {
+    mesh: &'a StridedMeshSlice<'b>,
+    next_indices_idx: usize,
+}
+
+impl<'a, 'b: 'a> Iterator for StridedMeshSliceIter<'a, 'b> {
+    type Item = ColoredVertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.next_indices_idx;
+
+        if idx >= self.mesh.indices.len() {
+            None
+        } else {
+            self.next_indices_idx += 1;
+            Some(self.mesh.vertex_at(idx))
+        }
+    }
+}
+
+/// Gets the vertex starting at the beginning of the given interleaved data.
+pub(crate) fn strided_vertex(vertex: &[f32], offsets: &AttributeOffsets) -> ColoredVertex {
+    // Absent attributes are zero since the vertex type requires them
+    ColoredVertex {
+        position: vec3_at(&vertex[offsets.position..], 0),
+        normal: match offsets.normal {
+            Some(o) => vec3_at(&vertex[o..], 0),
+            None => Vec3::new(0.0, 0.0, 0.0),
+        },
+        texcoords: match offsets.texcoords {
+            Some(o) => vec2_at(&vertex[o..], 0),
+            None => Vec2::new(0.0, 0.0),
+        },
+        color: offsets.color.map(|o| {
+            let c = &vertex[o..o + 4];
+            [c[0], c[1], c[2], c[3]]
+        }),
+    }
+}
+
+fn vec3_at(values: &[f32], idx: usize) -> Vec3 {
+    let v = &values[idx * 3..(idx + 1) * 3];
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn vec2_at(values: &[f32], idx: usize) -> Vec2 {
+    let v = &values[idx * 2..(idx + 1) * 2];
+    Vec2::new(v[0], v[1])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::{Normal, Position, Texcoords};
+    use mesh::{CustomAttribute, InterleavedIndexedMeshBuf, VertexLayout};
+
+    #[test]
+    fn test_slice_matches_buf() {
+        let buf = make_mesh();
+        let slice = buf.as_slice();
+
+        assert_eq!(slice.vertex_count(), buf.vertex_count());
+        for (a, b) in slice.vertices().zip(buf.vertices()) {
+            assert_eq!(a.position(), b.position());
+            assert_eq!(a.normal(), b.normal());
+            assert_eq!(a.texcoords(), b.texcoords());
+        }
+        assert_eq!(slice.triangle_indices(1), [2, 1, 3]);
+        assert_eq!(slice.color_at(5), buf.color_at(5));
+        assert_eq!(slice.tangent_at(5), buf.tangent_at(5));
+        assert_eq!(
+            slice.mesh_vertices().collect::<Vec<_>>(),
+            buf.mesh_vertices().collect::<Vec<_>>()
+        );
+
+        let owned = DeinterleavedIndexedMeshBuf::from(slice);
+        assert_eq!(owned.positions, buf.positions);
+        assert_eq!(owned.normals, buf.normals);
+        assert_eq!(owned.texcoords, buf.texcoords);
+        assert_eq!(owned.extra_texcoords, buf.extra_texcoords);
+        assert_eq!(owned.tangents, buf.tangents);
+        assert_eq!(owned.colors, buf.colors);
+        assert_eq!(owned.custom_attributes, buf.custom_attributes);
+        assert_eq!(owned.indices, buf.indices);
+    }
+
+    #[test]
+    fn test_strided_matches_interleaved() {
+        let buf = make_mesh();
+        let interleaved = InterleavedIndexedMeshBuf::with_layout(
+            &buf,
+            VertexLayout::positions_normals_texcoords(),
+        )
+        .unwrap();
+        let strided = StridedMeshSlice::new(
+            interleaved.vertex_data(),
+            interleaved.stride(),
+            interleaved.offsets().clone(),
+            &interleaved.indices,
+        )
+        .unwrap();
+
+        for (a, b) in strided.vertices().zip(buf.vertices()) {
+            assert_eq!(a.position(), b.position());
+            assert_eq!(a.normal(), b.normal());
+            assert_eq!(a.texcoords(), b.texcoords());
+        }
+
+        let owned = strided.to_buf();
+        assert_eq!(owned.positions, buf.positions);
+        assert_eq!(owned.normals, buf.normals);
+        assert_eq!(owned.texcoords, buf.texcoords);
+    }
+
+    #[test]
+    fn test_strided_checked() {
+        let data = [0.0; 10];
+        let offsets = AttributeOffsets {
+            normal: Some(3),
+            ..Default::default()
+        };
+
+        assert!(StridedMeshSlice::new(&data, 0, AttributeOffsets::default(), &[]).is_err());
+        assert_eq!(
+            StridedMeshSlice::new(&data, 5, offsets.clone(), &[0]).unwrap_err(),
+            vec![LayoutError::AttributeOffset {
+                attribute: "normal",
+                offset: 3,
+                stride: 5,
+            }]
+        );
+        // The second vertex is not padded
+        assert!(StridedMeshSlice::new(&data, 7, offsets.clone(), &[1]).is_err());
+        assert!(StridedMeshSlice::new(&data[..9], 6, AttributeOffsets::default(), &[1]).is_ok());
+        assert!(StridedMeshSlice::new(&data, 6, offsets, &[1]).is_err());
+    }
+
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            extra_texcoords: vec![vec![0.5, 0.5, 1.0, 0.5, 0.5, 1.0, 1.0, 1.0]],
+            colors: vec![0.25; 16],
+            indices: vec![0, 1, 2, 2, 1, 3],
+            ..Default::default()
+        };
+        mesh.generate_tangents().unwrap();
+        mesh.custom_attributes.insert(
+            String::from("region"),
+            CustomAttribute::U32(vec![1, 2, 3, 4]),
+        );
+        mesh
+    }
+}
@@ -0,0 +1,291 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::LayoutError;
+use super::mikktspace::corner_tangents;
+use geom::Vec3;
+use math::cross;
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Generates tangents from positions, normals and texture coordinates,
+    /// replacing existing tangents.
+    ///
+    /// The tangents are calculated with a port of the reference
+    /// implementation of [MikkTSpace](http://www.mikktspace.com/), the tangent
+    /// space used by Blender, Unity, Unreal and most texture bakers, so that
+    /// normal maps baked with these tools look the same on the mesh:
+    ///
+    /// * Tangents point along increasing `u` and are orthogonal to the normal,
+    /// * corners with equal position, normal and texture coordinates share a
+    ///   tangent if their triangles are connected through shared edges,
+    ///   averaged over the triangles and weighted by the angle at the corner,
+    /// * triangles with mirrored texture coordinates are not averaged with
+    ///   others, the fourth tangent component is `1.0` or `-1.0` depending on
+    ///   the texture orientation,
+    /// * the bitangent is `w * cross(normal, tangent)` and can be obtained
+    ///   with [`bitangent_at`](#method.bitangent_at).
+    ///
+    /// Vertices shared between triangles with different tangents are split.
+    /// Triangles without texture coordinate area take the tangent of their
+    /// neighbors. Like in the reference, they get the tangent `(1, 0, 0)` with
+    /// a sign of `-1.0` if they have no neighbors with texture coordinate area.
+    ///
+    /// Returns an error and leaves the mesh untouched if the mesh has no
+    /// normals or no texture coordinates for every vertex.
+    ///
+    /// ```
+    /// # extern crate aitios_geom;
+    /// # extern crate aitios_scene;
+    /// use aitios_scene::DeinterleavedIndexedMeshBuf;
+    /// use aitios_geom::Vec3;
+    ///
+    /// # fn main() {
+    /// let mut mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+    ///     texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// mesh.generate_tangents().unwrap();
+    /// assert_eq!(mesh.tangent_at(0), Some((Vec3::new(1.0, 0.0, 0.0), 1.0)));
+    /// assert_eq!(mesh.bitangent_at(0), Some(Vec3::new(0.0, 1.0, 0.0)));
+    /// # }
+    /// ```
+    pub fn generate_tangents(&mut self) -> Result<(), LayoutError> {
+        let vertex_count = self.positions.len() / 3;
+        let required: [(&'static str, usize, usize); 2] = [
+            ("normal", self.normals.len(), vertex_count * 3),
+            ("texcoord", self.texcoords.len(), vertex_count * 2),
+        ];
+        for &(attribute, len, expected) in &required {
+            if len != expected {
+                return Err(LayoutError::AttributeLength {
+                    attribute,
+                    len,
+                    expected,
+                });
+            }
+        }
+
+        let corner_values = corner_tangents(self);
+        self.tangents = self.split_vertices_by_corner(&corner_values, 4);
+        Ok(())
+    }
+
+    /// Gets the tangent of the vertex at the given index into the index
+    /// vector, together with the sign of the bitangent, or `None` if the
+    /// mesh has no tangents.
+    pub fn tangent_at(&self, index_index: usize) -> Option<(Vec3, f32)> {
+        self.as_standard_slice().tangent_at(index_index)
+    }
+
+    /// Calculates the bitangent of the vertex at the given index into the
+    /// index vector as `w * cross(normal, tangent)`, or `None` if the mesh
+    /// has no tangents.
+    pub fn bitangent_at(&self, index_index: usize) -> Option<Vec3> {
+        self.tangent_at(index_index).map(|(tangent, sign)| {
+            let normal = self.vertex_normal(self.indices[index_index] as usize);
+            cross(normal, tangent) * sign
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::length;
+
+    #[test]
+    fn test_quad_tangents() {
+        let mut mesh = make_quad(false);
+        mesh.generate_tangents().unwrap();
+
+        assert_eq!(mesh.positions.len(), 4 * 3);
+        assert_eq!(mesh.tangents.len(), 4 * 4);
+        for idx in 0..mesh.indices.len() {
+            let (tangent, sign) = mesh.tangent_at(idx).unwrap();
+            assert_close(tangent, Vec3::new(1.0, 0.0, 0.0));
+            assert_eq!(sign, 1.0);
+            assert_close(mesh.bitangent_at(idx).unwrap(), Vec3::new(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_mirrored_texcoords() {
+        let mut mesh = make_quad(true);
+        mesh.generate_tangents().unwrap();
+
+        let (tangent, sign) = mesh.tangent_at(0).unwrap();
+        assert_close(tangent, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(sign, -1.0);
+        // The bitangent still follows v
+        assert_close(mesh.bitangent_at(0).unwrap(), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_split_at_mirror_seam() {
+        // Left quad has regular, right quad mirrored texcoords, sharing the middle edge
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                -1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+                1.0, 0.0,
+            ],
+            normals: [0.0, 0.0, 1.0].iter().cycle().take(18).cloned().collect(),
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2, 0, 2, 3, 1, 4, 5, 1, 5, 2],
+            ..Default::default()
+        };
+
+        mesh.generate_tangents().unwrap();
+
+        // The two vertices in the middle are split
+        assert_eq!(mesh.positions.len(), 8 * 3);
+        assert_eq!(mesh.tangent_at(0).unwrap().1, 1.0);
+        assert_eq!(mesh.tangent_at(6).unwrap().1, -1.0);
+        assert_close(mesh.tangent_at(1).unwrap().0, Vec3::new(1.0, 0.0, 0.0));
+        assert_close(mesh.tangent_at(6).unwrap().0, Vec3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        // Tangents per corner that bevy_mikktspace 0.16.1, a translation of
+        // the reference implementation, generates for the fixture
+        let expected = [
+            [0.99980205, -0.019798059, 0.001979833, 1.0],
+            [0.9283787, -0.002739682, 0.37162542, 1.0],
+            [0.92841405, -0.0017748153, 0.3715431, 1.0],
+            [0.99980205, -0.019798059, 0.001979833, 1.0],
+            [0.92841405, -0.0017748153, 0.3715431, 1.0],
+            [0.99980205, -0.019798057, 0.0019798328, 1.0],
+            [0.9283787, -0.002739682, 0.37162542, 1.0],
+            [0.7076728, 0.012415314, 0.7064312, 1.0],
+            [0.70767283, 0.012415315, 0.70643127, 1.0],
+            [0.9283787, -0.002739682, 0.37162542, 1.0],
+            [0.70767283, 0.012415315, 0.70643127, 1.0],
+            [0.92841405, -0.0017748153, 0.3715431, 1.0],
+            [-0.70677245, 0.006396134, -0.70741206, -1.0],
+            [-0.58128846, -0.0010701373, -0.81369686, -1.0],
+            [-0.5813031, -0.0013848005, -0.8136859, -1.0],
+            [-0.70677245, 0.006396134, -0.70741206, -1.0],
+            [-0.5813031, -0.0013848005, -0.8136859, -1.0],
+            [-0.7067724, 0.006396135, -0.70741206, -1.0],
+            [-0.58128846, -0.0010701373, -0.81369686, -1.0],
+            [-0.53006816, -0.0015544523, -0.84795356, -1.0],
+            [-0.5300681, -0.0015544522, -0.84795356, -1.0],
+            [-0.58128846, -0.0010701373, -0.81369686, -1.0],
+            [-0.5300681, -0.0015544522, -0.84795356, -1.0],
+            [-0.5813031, -0.0013848005, -0.8136859, -1.0],
+            [0.14132121, -0.98505086, 0.09850509, 1.0],
+            [0.14142136, -0.9899495, 0.0, 1.0],
+            [0.14142136, -0.9899495, 0.0, 1.0],
+            [0.99980205, -0.019798057, 0.0019798328, 1.0],
+            [0.99980205, -0.019798057, 0.0019798328, 1.0],
+            [0.92841405, -0.0017748153, 0.3715431, 1.0],
+        ];
+
+        let mut mesh = make_fixture();
+        mesh.generate_tangents().unwrap();
+
+        for (idx, expected) in expected.iter().enumerate() {
+            let (tangent, sign) = mesh.tangent_at(idx).unwrap();
+            let expected_tangent = Vec3::new(expected[0], expected[1], expected[2]);
+            assert!(
+                length(tangent - expected_tangent) < 0.000_001,
+                "Corner {}: {:?} != {:?}",
+                idx,
+                tangent,
+                expected_tangent
+            );
+            assert_eq!(sign, expected[3], "Corner {}", idx);
+        }
+
+        // The first vertex is split for the triangle that only touches it
+        assert_ne!(mesh.indices[0], mesh.indices[24]);
+        assert_eq!(mesh.indices[0], mesh.indices[3]);
+    }
+
+    #[test]
+    fn test_missing_attributes() {
+        let mut mesh = make_quad(false);
+        mesh.texcoords.clear();
+
+        assert_eq!(
+            mesh.generate_tangents(),
+            Err(LayoutError::AttributeLength {
+                attribute: "texcoord",
+                len: 0,
+                expected: 8,
+            })
+        );
+        assert!(mesh.tangents.is_empty());
+    }
+
+    #[test]
+    fn test_no_tangents() {
+        let mesh = make_quad(false);
+        assert!(mesh.tangent_at(0).is_none());
+        assert!(mesh.bitangent_at(0).is_none());
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            length(actual - expected) < 0.0001,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    fn make_quad(mirrored: bool) -> DeinterleavedIndexedMeshBuf {
+        let u = |u: f32| if mirrored { 1.0 - u } else { u };
+
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            texcoords: vec![u(0.0), 0.0, u(1.0), 0.0, u(1.0), 1.0, u(0.0), 1.0],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..Default::default()
+        }
+    }
+
+    /// Regular island on the left and a mirrored island on the right, meeting
+    /// at a UV seam at x = 2.5, a triangle touching the first vertex without
+    /// sharing an edge and a degenerate triangle
+    fn make_fixture() -> DeinterleavedIndexedMeshBuf {
+        let mut mesh = DeinterleavedIndexedMeshBuf::default();
+        let islands: [(&[f32], f32, f32, f32); 2] = [
+            (&[0.0, 1.0, 2.5], 0.0, 0.2, 0.0),
+            (&[2.5, 3.5, 4.0], 1.25, -0.3, 0.5),
+        ];
+
+        for &(xs, u0, du, v0) in &islands {
+            let first = (mesh.positions.len() / 3) as u32;
+            for &x in xs {
+                for &y in &[0.0_f32, 1.0] {
+                    let (nx, ny, nz) = (-0.4 * x, 0.1, 1.0);
+                    let len = (nx * nx + ny * ny + nz * nz).sqrt();
+                    mesh.positions
+                        .extend_from_slice(&[x, y, 0.2 * x * x - 0.1 * y]);
+                    mesh.normals
+                        .extend_from_slice(&[nx / len, ny / len, nz / len]);
+                    mesh.texcoords
+                        .extend_from_slice(&[u0 + du * x, v0 + 0.5 * y]);
+                }
+            }
+            for quad in 0..(xs.len() as u32 - 1) {
+                let (a, d) = (first + quad * 2, first + quad * 2 + 1);
+                let (b, c) = (a + 2, d + 2);
+                mesh.indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+
+        mesh.positions
+            .extend_from_slice(&[-1.0, -0.5, 0.2, 0.5, -1.0, 0.0]);
+        mesh.normals
+            .extend_from_slice(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        mesh.texcoords.extend_from_slice(&[0.1, -0.3, 0.2, 0.1]);
+        mesh.indices.extend_from_slice(&[0, 12, 13, 1, 1, 3]);
+
+        mesh
+    }
+}
@@ -0,0 +1,124 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use geom::Vec2;
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Gets the amount of texture coordinate sets, including set 0 in
+    /// `texcoords` if it or any additional set is present.
+    pub fn texcoord_set_count(&self) -> usize {
+        if self.extra_texcoords.is_empty() && self.texcoords.is_empty() {
+            0
+        } else {
+            1 + self.extra_texcoords.len()
+        }
+    }
+
+    /// Gets the texture coordinates of the given UV set with two values per
+    /// vertex, where set 0 is `texcoords` and set `i + 1` is `extra_texcoords[i]`.
+    ///
+    /// Returns `None` if the mesh has no such set.
+    ///
+    /// ```
+    /// use aitios_scene::DeinterleavedIndexedMeshBuf;
+    ///
+    /// let mut mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+    ///     // Tiled UVs of the artist
+    ///     texcoords: vec![0.0, 0.0, 4.0, 0.0, 0.0, 4.0],
+    ///     indices: vec![0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// // Non-overlapping UVs for baking
+    /// let bake_set = mesh.push_texcoord_set(vec![0.0, 0.0, 0.5, 0.0, 0.0, 0.5]);
+    ///
+    /// assert_eq!(bake_set, 1);
+    /// assert_eq!(mesh.texcoord_set_count(), 2);
+    /// assert_eq!(mesh.texcoord_set(1), Some(&[0.0, 0.0, 0.5, 0.0, 0.0, 0.5][..]));
+    /// assert!(mesh.check_layout().is_ok());
+    /// ```
+    pub fn texcoord_set(&self, set: usize) -> Option<&[f32]> {
+        let texcoords = if set == 0 {
+            &self.texcoords
+        } else {
+            self.extra_texcoords.get(set - 1)?
+        };
+
+        if texcoords.is_empty() {
+            None
+        } else {
+            Some(texcoords)
+        }
+    }
+
+    /// Adds a texture coordinate set with two values per vertex and returns
+    /// the number of the set.
+    ///
+    /// If the mesh has no texture coordinates yet, they become set 0.
+    pub fn push_texcoord_set(&mut self, texcoords: Vec<f32>) -> usize {
+        if self.texcoord_set_count() == 0 {
+            self.texcoords = texcoords;
+            0
+        } else {
+            self.extra_texcoords.push(texcoords);
+            self.extra_texcoords.len()
+        }
+    }
+
+    /// Gets the texture coordinates in the given UV set of the vertex at the
+    /// given index into the index vector, or `None` if the mesh has no such set.
+    pub fn texcoords_at(&self, index_index: usize, set: usize) -> Option<Vec2> {
+        let idx = self.indices[index_index] as usize;
+        self.texcoord_set(set)
+            .map(|t| Vec2::new(t[idx * 2], t[idx * 2 + 1]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::{MeshVertex, NormalWeighting, VertexLayout};
+
+    #[test]
+    fn test_sets_survive_split() {
+        let mut mesh = make_mesh();
+        mesh.generate_normals(NormalWeighting::Flat);
+
+        assert_eq!(mesh.texcoord_set_count(), 2);
+        assert_eq!(mesh.texcoords_at(3, 0), Some(Vec2::new(2.0, 0.0)));
+        assert_eq!(mesh.texcoords_at(3, 1), Some(Vec2::new(0.5, 0.0)));
+        assert_eq!(mesh.texcoords_at(3, 2), None);
+        assert!(mesh.check_layout().is_ok());
+    }
+
+    #[test]
+    fn test_sets_survive_collect() {
+        let mesh = make_mesh();
+
+        let vertices: Vec<MeshVertex> = mesh.mesh_vertices().collect();
+        assert_eq!(vertices[5].extra_texcoords, vec![Vec2::new(0.5, 0.5)]);
+
+        let collected: DeinterleavedIndexedMeshBuf = vertices.into_iter().collect();
+        assert_eq!(
+            collected.layout(),
+            VertexLayout {
+                texcoords: true,
+                extra_texcoord_sets: 1,
+                ..VertexLayout::positions_only()
+            }
+        );
+        assert_eq!(collected.texcoords_at(5, 1), Some(Vec2::new(0.5, 0.5)));
+    }
+
+    /// Two triangles sharing an edge, with tiled UVs in set 0 and UVs
+    /// for baking in set 1
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0],
+            texcoords: vec![0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 2.0, 2.0],
+            indices: vec![0, 1, 2, 1, 2, 3],
+            ..Default::default()
+        };
+        mesh.push_texcoord_set(vec![0.0, 0.0, 0.5, 0.0, 0.0, 0.5, 0.5, 0.5]);
+        mesh
+    }
+}
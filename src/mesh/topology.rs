@@ -0,0 +1,473 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::weld::{WeldTolerance, Welder};
+use geom::Vec3;
+use std::collections::{HashMap, HashSet};
+
+/// One of the three edges of a triangle, where edge `i` runs from corner `i`
+/// to corner `(i + 1) % 3` of the triangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TriangleEdge {
+    pub triangle: usize,
+    pub edge: usize,
+}
+
+/// Adjacency information of the triangles of a mesh, e.g. for walking from
+/// a triangle to its neighbours in a flow simulation.
+///
+/// Vertices with matching positions are treated as the same vertex, even if
+/// their other attributes differ, so that triangles separated by hard edges
+/// or texture seams are still connected. These welded vertices are numbered
+/// independently of the vertices of the mesh, see
+/// [`welded_vertex`](#method.welded_vertex).
+///
+/// Triangles are numbered in the order of the indices of the mesh. Incomplete
+/// triangles at the end of the indices are ignored.
+///
+/// ```
+/// use aitios_scene::DeinterleavedIndexedMeshBuf;
+///
+/// // Two unconnected triangles forming a quad, as e.g. loaded with flat normals
+/// let mesh = DeinterleavedIndexedMeshBuf {
+///     positions: vec![
+///         0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+///         0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0,
+///     ],
+///     indices: vec![0, 1, 2, 3, 4, 5],
+///     ..Default::default()
+/// };
+///
+/// let topology = mesh.topology();
+/// assert_eq!(topology.welded_vertex_count(), 4);
+/// assert_eq!(topology.neighbour(0, 1), Some(1));
+/// assert_eq!(topology.boundary_edges().len(), 4);
+/// assert!(topology.non_manifold_edges().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Topology {
+    /// The welded vertex of each vertex of the mesh.
+    welded_vertices: Vec<u32>,
+    welded_vertex_count: usize,
+    /// The welded vertex at each triangle corner, three per triangle.
+    corners: Vec<u32>,
+    /// The edge each triangle edge belongs to, three per triangle.
+    corner_edges: Vec<u32>,
+    /// The triangle edges along each edge, encoded as `triangle * 3 + edge`.
+    edges: Vec<Vec<u32>>,
+    /// The triangles touching each welded vertex.
+    vertex_triangles: Vec<Vec<u32>>,
+}
+
+impl Topology {
+    /// Builds the topology of the given mesh, treating vertices with positions
+    /// within the given tolerance as the same vertex. Negative and positive
+    /// zero are considered equal, also with `WeldTolerance::Exact`.
+    ///
+    /// # Panics
+    /// If an index is out of range.
+    pub fn new(mesh: &DeinterleavedIndexedMeshBuf, position_tolerance: WeldTolerance) -> Self {
+        let vertex_count = mesh.positions.len() / 3;
+        let welded = Welder::new()
+            .position_tolerance(position_tolerance)
+            .collect((0..vertex_count).map(|idx| {
+                // Adding positive zero turns negative zero from mirroring into positive zero
+                mesh.vertex_position(idx) + Vec3::new(0.0, 0.0, 0.0)
+            }));
+        let welded_vertices = welded.indices;
+        let welded_vertex_count = welded.positions.len() / 3;
+
+        let corners: Vec<u32> = mesh
+            .indices
+            .chunks(3)
+            .filter(|tri| tri.len() == 3)
+            .flat_map(|tri| tri.iter().map(|&idx| welded_vertices[idx as usize]))
+            .collect();
+
+        let mut edge_ids = HashMap::new();
+        let mut edges: Vec<Vec<u32>> = Vec::new();
+        let mut corner_edges = Vec::with_capacity(corners.len());
+        let mut vertex_triangles = vec![Vec::new(); welded_vertex_count];
+        for (corner, &from) in corners.iter().enumerate() {
+            let triangle = corner / 3;
+            let to = corners[triangle * 3 + (corner + 1) % 3];
+            let key = (from.min(to), from.max(to));
+
+            let edge_id = *edge_ids.entry(key).or_insert_with(|| {
+                edges.push(Vec::new());
+                (edges.len() - 1) as u32
+            });
+            edges[edge_id as usize].push(corner as u32);
+            corner_edges.push(edge_id);
+
+            // Degenerate triangles may touch a vertex more than once
+            let triangles: &mut Vec<u32> = &mut vertex_triangles[from as usize];
+            if triangles.last() != Some(&(triangle as u32)) {
+                triangles.push(triangle as u32);
+            }
+        }
+
+        Topology {
+            welded_vertices,
+            welded_vertex_count,
+            corners,
+            corner_edges,
+            edges,
+            vertex_triangles,
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.corners.len() / 3
+    }
+
+    /// Gets the amount of distinct vertex positions.
+    pub fn welded_vertex_count(&self) -> usize {
+        self.welded_vertex_count
+    }
+
+    /// Gets the welded vertex of the vertex with the given index into the
+    /// attribute vectors of the mesh.
+    pub fn welded_vertex(&self, vertex_idx: usize) -> usize {
+        self.welded_vertices[vertex_idx] as usize
+    }
+
+    /// Gets the welded vertices at the corners of a triangle.
+    pub fn triangle_vertices(&self, triangle: usize) -> [usize; 3] {
+        let tri = &self.corners[triangle * 3..(triangle + 1) * 3];
+        [tri[0] as usize, tri[1] as usize, tri[2] as usize]
+    }
+
+    /// Gets the triangle on the other side of the given edge of a triangle,
+    /// or `None` if the edge is on the boundary, shared by more than two
+    /// triangles or only shared with another edge of the same degenerate
+    /// triangle.
+    pub fn neighbour(&self, triangle: usize, edge: usize) -> Option<usize> {
+        let corner = (triangle * 3 + edge) as u32;
+        match *self.triangle_edges_along(triangle, edge) {
+            [a, b] => {
+                let other = if a == corner { b } else { a };
+                Some(other as usize / 3).filter(|&other| other != triangle)
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets all other triangles that share an edge with the given triangle,
+    /// including the ones sharing non-manifold edges, in ascending order.
+    pub fn neighbours(&self, triangle: usize) -> Vec<usize> {
+        let mut neighbours: Vec<usize> = (0..3)
+            .flat_map(|edge| self.triangle_edges_along(triangle, edge))
+            .map(|&corner| corner as usize / 3)
+            .filter(|&other| other != triangle)
+            .collect();
+        neighbours.sort();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// Checks whether no other triangle shares the given edge of a triangle.
+    pub fn is_boundary(&self, triangle: usize, edge: usize) -> bool {
+        self.triangle_edges_along(triangle, edge).len() == 1
+    }
+
+    /// Gets all triangle edges that no other triangle shares.
+    pub fn boundary_edges(&self) -> Vec<TriangleEdge> {
+        self.edges
+            .iter()
+            .filter(|along| along.len() == 1)
+            .map(|along| triangle_edge(along[0]))
+            .collect()
+    }
+
+    /// Gets the edges that are shared by more than two triangles, each with
+    /// all the triangle edges along it.
+    pub fn non_manifold_edges(&self) -> Vec<Vec<TriangleEdge>> {
+        self.edges
+            .iter()
+            .filter(|along| along.len() > 2)
+            .map(|along| along.iter().cloned().map(triangle_edge).collect())
+            .collect()
+    }
+
+    /// Gets the triangles touching the given welded vertex, in ascending order.
+    pub fn vertex_triangles(&self, welded_vertex: usize) -> Vec<usize> {
+        self.vertex_triangles[welded_vertex]
+            .iter()
+            .map(|&triangle| triangle as usize)
+            .collect()
+    }
+
+    /// Iterates over the welded vertices connected to the given welded vertex
+    /// by an edge, in the order of the triangles around the vertex, walking
+    /// from triangle to triangle over the edges at the vertex.
+    ///
+    /// If the triangles have counter-clockwise winding, the vertices are
+    /// visited counter-clockwise when looking at the front faces. On the
+    /// boundary, iteration starts at the vertex across a boundary edge and
+    /// ends at the vertex across the other one. Around inner vertices, it
+    /// starts at an arbitrary vertex and visits each vertex once.
+    ///
+    /// Non-manifold edges are treated like boundary edges, and degenerate
+    /// triangles are skipped. At non-manifold vertices, where the triangles
+    /// around the vertex form more than one fan, the fans are visited one
+    /// after another and vertices on more than one fan are visited once for
+    /// each fan.
+    ///
+    /// ```
+    /// use aitios_scene::DeinterleavedIndexedMeshBuf;
+    ///
+    /// // Three triangles around vertex 0, with a boundary between 1 and 3
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![
+    ///         0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0,
+    ///     ],
+    ///     indices: vec![0, 2, 3, 0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let ring: Vec<usize> = mesh.topology().one_ring(0).collect();
+    /// assert_eq!(ring, vec![1, 2, 3]);
+    /// ```
+    pub fn one_ring(&self, welded_vertex: usize) -> impl Iterator<Item = usize> {
+        let triangles = &self.vertex_triangles[welded_vertex];
+        let mut visited = HashSet::new();
+        let mut ring = Vec::new();
+
+        for &first in triangles {
+            let first = first as usize;
+            if visited.contains(&first) || self.is_degenerate(first) {
+                continue;
+            }
+
+            // Walk backwards to the first triangle of the fan, or around
+            // the vertex back to this triangle if the fan is closed
+            let mut start = (first, self.edges_at(first, welded_vertex)[0]);
+            let mut closed = false;
+            for _ in 0..triangles.len() {
+                match self.cross(start.0, start.1, welded_vertex) {
+                    Some((triangle, _)) if triangle == first => {
+                        start = (first, self.edges_at(first, welded_vertex)[0]);
+                        closed = true;
+                        break;
+                    }
+                    Some(next) if !visited.contains(&next.0) => start = next,
+                    _ => break,
+                }
+            }
+
+            let (mut triangle, start_edge) = start;
+            ring.push(self.other_end(triangle, start_edge, welded_vertex));
+            let [a, b] = self.edges_at(triangle, welded_vertex);
+            let mut edge = if a == start_edge { b } else { a };
+
+            loop {
+                visited.insert(triangle);
+                match self.cross(triangle, edge, welded_vertex) {
+                    Some((next, _)) if next == start.0 && closed => break,
+                    Some((next, next_edge)) if !visited.contains(&next) => {
+                        ring.push(self.other_end(triangle, edge, welded_vertex));
+                        triangle = next;
+                        edge = next_edge;
+                    }
+                    _ => {
+                        ring.push(self.other_end(triangle, edge, welded_vertex));
+                        break;
+                    }
+                }
+            }
+        }
+
+        ring.into_iter()
+    }
+
+    /// Gets the two edges of a triangle that touch the given welded vertex,
+    /// starting with the one leaving the vertex.
+    fn edges_at(&self, triangle: usize, welded_vertex: usize) -> [usize; 2] {
+        let corner = (0..3)
+            .find(|&corner| self.corners[triangle * 3 + corner] as usize == welded_vertex)
+            .unwrap();
+        [corner, (corner + 2) % 3]
+    }
+
+    /// Gets the welded vertex at the end of the given edge of a triangle that
+    /// is not the given welded vertex.
+    fn other_end(&self, triangle: usize, edge: usize, welded_vertex: usize) -> usize {
+        let from = self.corners[triangle * 3 + edge] as usize;
+        let to = self.corners[triangle * 3 + (edge + 1) % 3] as usize;
+        if from == welded_vertex {
+            to
+        } else {
+            from
+        }
+    }
+
+    /// Crosses the given edge of a triangle at the given welded vertex and
+    /// gets the triangle on the other side along with its other edge at the
+    /// vertex, or `None` if there is no single non-degenerate neighbour.
+    fn cross(&self, triangle: usize, edge: usize, welded_vertex: usize) -> Option<(usize, usize)> {
+        let next = self
+            .neighbour(triangle, edge)
+            .filter(|&next| !self.is_degenerate(next))?;
+        let shared = self.corner_edges[triangle * 3 + edge];
+        let [a, b] = self.edges_at(next, welded_vertex);
+        if self.corner_edges[next * 3 + a] == shared {
+            Some((next, b))
+        } else {
+            Some((next, a))
+        }
+    }
+
+    /// Checks whether a triangle has the same welded vertex at two corners.
+    fn is_degenerate(&self, triangle: usize) -> bool {
+        let [a, b, c] = self.triangle_vertices(triangle);
+        a == b || b == c || a == c
+    }
+
+    /// Gets the triangle edges along the same edge as the given one,
+    /// including itself, encoded as `triangle * 3 + edge`.
+    fn triangle_edges_along(&self, triangle: usize, edge: usize) -> &[u32] {
+        &self.edges[self.corner_edges[triangle * 3 + edge] as usize]
+    }
+}
+
+fn triangle_edge(corner: u32) -> TriangleEdge {
+    TriangleEdge {
+        triangle: corner as usize / 3,
+        edge: corner as usize % 3,
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Builds the [`Topology`](struct.Topology.html) of the mesh, treating
+    /// vertices with bitwise identical positions as the same vertex, except
+    /// that negative and positive zero are equal.
+    pub fn topology(&self) -> Topology {
+        Topology::new(self, WeldTolerance::Exact)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fan() {
+        let topology = make_fan().topology();
+
+        assert_eq!(topology.triangle_count(), 4);
+        assert_eq!(topology.welded_vertex_count(), 6);
+        assert_eq!(topology.neighbours(1), vec![0, 2]);
+        assert_eq!(topology.neighbour(0, 2), Some(1));
+        assert_eq!(topology.neighbour(0, 0), None);
+        assert!(topology.is_boundary(0, 1));
+        assert_eq!(topology.boundary_edges().len(), 6);
+        assert_eq!(topology.vertex_triangles(0), vec![0, 1, 2, 3]);
+        assert_eq!(
+            topology.one_ring(0).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(topology.one_ring(2).collect::<Vec<_>>(), vec![3, 0, 1]);
+    }
+
+    #[test]
+    fn test_one_ring_order() {
+        // Four triangles closed around vertex 0, the last one flipped
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0, 0.0,
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 1, 4],
+            ..Default::default()
+        };
+        let ring: Vec<usize> = mesh.topology().one_ring(0).collect();
+        assert_eq!(ring, vec![1, 2, 3, 4]);
+
+        // Two fans only touching at vertex 0
+        mesh.indices = vec![0, 1, 2, 0, 3, 4];
+        let ring: Vec<usize> = mesh.topology().one_ring(0).collect();
+        assert_eq!(ring, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_non_manifold() {
+        let mut mesh = make_fan();
+        // A fin on the edge between the first two triangles
+        mesh.positions.extend(&[0.0, 0.0, 1.0]);
+        mesh.indices.extend(&[0, 2, 6]);
+        let topology = mesh.topology();
+
+        let non_manifold = topology.non_manifold_edges();
+        assert_eq!(non_manifold.len(), 1);
+        assert_eq!(
+            non_manifold[0],
+            vec![
+                TriangleEdge {
+                    triangle: 0,
+                    edge: 2
+                },
+                TriangleEdge {
+                    triangle: 1,
+                    edge: 0
+                },
+                TriangleEdge {
+                    triangle: 4,
+                    edge: 0
+                },
+            ]
+        );
+        assert_eq!(topology.neighbour(1, 0), None);
+        assert_eq!(topology.neighbours(1), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_epsilon() {
+        // Two triangles sharing an edge, with a slightly displaced duplicate of vertex 0
+        let mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1e-4, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0,
+                1.0, 0.0,
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5],
+            ..Default::default()
+        };
+
+        let exact = mesh.topology();
+        assert_eq!(exact.welded_vertex_count(), 5);
+        assert_eq!(exact.neighbour(0, 2), None);
+
+        let welded = Topology::new(&mesh, WeldTolerance::Epsilon(1e-3));
+        assert_eq!(welded.welded_vertex_count(), 4);
+        assert_eq!(welded.welded_vertex(3), welded.welded_vertex(0));
+        assert_eq!(welded.neighbour(0, 2), Some(1));
+    }
+
+    #[test]
+    fn test_signed_zero_and_degenerate() {
+        // A quad mirrored at x = 0, with negative zero on the mirrored side
+        let mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -0.0, 0.0, 0.0, -0.0, 1.0, 0.0, -1.0,
+                0.0, 0.0, 1.0, 1.0, 0.0,
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5, 1, 1, 6],
+            ..Default::default()
+        };
+        let topology = mesh.topology();
+
+        assert_eq!(topology.welded_vertex_count(), 5);
+        assert_eq!(topology.neighbour(0, 2), Some(1));
+        assert_eq!(topology.neighbour(2, 1), None);
+        assert_eq!(topology.neighbours(2), Vec::<usize>::new());
+    }
+
+    /// Four triangles around vertex 0, not closed between the first and last
+    fn make_fan() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, -1.0, 1.0, 0.0, -1.0,
+                0.0, 0.0,
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5],
+            ..Default::default()
+        }
+    }
+}
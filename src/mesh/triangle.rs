@@ -53,6 +53,7 @@ mod test {
             normals: vec![],
             texcoords: vec![],
             indices: vec![],
+            ..Default::default()
         };
         let mut iter = TriangleMeshIter::new(buf.into_iter());
 
@@ -72,6 +73,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![3, 4, 5, 0, 1, 2],
+            ..Default::default()
         };
         let mut iter = TriangleMeshIter::new((&buf).vertices());
 
@@ -103,6 +105,7 @@ mod test {
             ],
             texcoords: vec![0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.7, 0.7, 0.7, 0.7, 0.7, 0.7],
             indices: vec![3, 4, 5, 0, 1],
+            ..Default::default()
         };
         let mut iter = TriangleMeshIter::new(buf.into_iter());
 
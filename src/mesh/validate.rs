@@ -0,0 +1,295 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::LayoutError;
+use math::cross;
+use std::fmt;
+
+/// All problems found in a `DeinterleavedIndexedMeshBuf` by
+/// [`validate`](struct.DeinterleavedIndexedMeshBuf.html#method.validate).
+///
+/// Vertices are identified by their index into the attribute vectors, and
+/// triangles by their position in the index vector divided by three.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    /// Attributes with inconsistent lengths and indices referencing vertices
+    /// that do not exist, as found by
+    /// [`check_layout`](struct.DeinterleavedIndexedMeshBuf.html#method.check_layout).
+    pub layout_errors: Vec<LayoutError>,
+    /// The amount of indices at the end that do not form a complete triangle.
+    pub trailing_indices: usize,
+    /// Vertices with a NaN or infinite position component.
+    pub non_finite_positions: Vec<usize>,
+    /// Triangles that reference the same vertex more than once or have zero area.
+    pub degenerate_triangles: Vec<usize>,
+    /// Vertices that are not referenced by any index.
+    pub unreferenced_vertices: Vec<usize>,
+}
+
+impl ValidationReport {
+    /// Checks whether no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.layout_errors.is_empty()
+            && self.trailing_indices == 0
+            && self.non_finite_positions.is_empty()
+            && self.degenerate_triangles.is_empty()
+            && self.unreferenced_vertices.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "Mesh is valid");
+        }
+
+        write!(f, "Mesh has problems:")?;
+        for error in &self.layout_errors {
+            write!(f, "\n* {}", error)?;
+        }
+        if self.trailing_indices > 0 {
+            write!(
+                f,
+                "\n* {} indices at the end do not form a triangle",
+                self.trailing_indices
+            )?;
+        }
+        if !self.non_finite_positions.is_empty() {
+            write!(
+                f,
+                "\n* {} vertices have non-finite positions",
+                self.non_finite_positions.len()
+            )?;
+        }
+        if !self.degenerate_triangles.is_empty() {
+            write!(
+                f,
+                "\n* {} triangles are degenerate",
+                self.degenerate_triangles.len()
+            )?;
+        }
+        if !self.unreferenced_vertices.is_empty() {
+            write!(
+                f,
+                "\n* {} vertices are not referenced",
+                self.unreferenced_vertices.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Finds all problems of the mesh, from inconsistent attribute lengths
+    /// that make iteration panic to degenerate triangles and unreferenced
+    /// vertices that merely waste memory or computation.
+    ///
+    /// ```
+    /// use aitios_scene::DeinterleavedIndexedMeshBuf;
+    ///
+    /// let mesh = DeinterleavedIndexedMeshBuf {
+    ///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 5.0, 5.0, 5.0],
+    ///     indices: vec![0, 1, 2, 0, 0, 1, 2],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let report = mesh.validate();
+    /// assert_eq!(report.trailing_indices, 1);
+    /// assert_eq!(report.degenerate_triangles, vec![1]);
+    /// assert_eq!(report.unreferenced_vertices, vec![3]);
+    /// ```
+    pub fn validate(&self) -> ValidationReport {
+        let vertex_count = self.positions.len() / 3;
+        let layout_errors = self.check_layout().err().unwrap_or_default();
+
+        let non_finite_positions = (0..vertex_count)
+            .filter(|&idx| !self.position_is_finite(idx))
+            .collect();
+
+        let degenerate_triangles = self
+            .indices
+            .chunks(3)
+            .enumerate()
+            .filter(|&(_, tri)| tri.len() == 3 && self.is_degenerate(tri))
+            .map(|(triangle, _)| triangle)
+            .collect();
+
+        let mut referenced = vec![false; vertex_count];
+        for &idx in &self.indices {
+            if let Some(referenced) = referenced.get_mut(idx as usize) {
+                *referenced = true;
+            }
+        }
+        let unreferenced_vertices = (0..vertex_count).filter(|&idx| !referenced[idx]).collect();
+
+        ValidationReport {
+            layout_errors,
+            trailing_indices: self.indices.len() % 3,
+            non_finite_positions,
+            degenerate_triangles,
+            unreferenced_vertices,
+        }
+    }
+
+    /// Fixes the problems found by [`validate`](#method.validate) and returns
+    /// the report from before the repair.
+    ///
+    /// * Positions are truncated to a multiple of three values,
+    /// * other attributes with inconsistent lengths are cleared, or removed if
+    ///   they are custom attributes,
+    /// * trailing indices are removed,
+    /// * triangles with out-of-range indices, non-finite positions or zero area
+    ///   are removed,
+    /// * unreferenced vertices are removed and the remaining vertices keep
+    ///   their order.
+    ///
+    /// Afterwards, the mesh is valid.
+    pub fn repair(&mut self) -> ValidationReport {
+        let report = self.validate();
+        if report.is_valid() {
+            return report;
+        }
+
+        let vertex_count = self.positions.len() / 3;
+        self.positions.truncate(vertex_count * 3);
+
+        for error in &report.layout_errors {
+            match *error {
+                LayoutError::AttributeLength { attribute, .. } => match attribute {
+                    "normal" => self.normals.clear(),
+                    "texcoord" => self.texcoords.clear(),
+                    "tangent" => self.tangents.clear(),
+                    "color" => self.colors.clear(),
+                    _ => (),
+                },
+                // Clear instead of removing to keep the numbers of the other sets
+                LayoutError::TexcoordSetLength { set, .. } => self.extra_texcoords[set - 1].clear(),
+                LayoutError::CustomAttributeLength { ref name, .. }
+                | LayoutError::CustomAttributeComponents { ref name, .. } => {
+                    self.custom_attributes.remove(name);
+                }
+                // Offsets are only checked for strided slices
+                LayoutError::IndexOutOfRange { .. } | LayoutError::AttributeOffset { .. } => (),
+            }
+        }
+
+        let triangles: Vec<u32> = self
+            .indices
+            .chunks(3)
+            .filter(|tri| {
+                tri.len() == 3
+                    && tri.iter().all(|&idx| {
+                        (idx as usize) < vertex_count && self.position_is_finite(idx as usize)
+                    })
+                    && !self.is_degenerate(tri)
+            })
+            .flat_map(|tri| tri.iter().cloned())
+            .collect();
+
+        // Keep referenced vertices in their original order
+        *self = self.submesh(&triangles);
+
+        report
+    }
+
+    fn position_is_finite(&self, vertex_idx: usize) -> bool {
+        self.positions[vertex_idx * 3..(vertex_idx + 1) * 3]
+            .iter()
+            .all(|c| c.is_finite())
+    }
+
+    /// Checks whether a triangle references a vertex more than once or has
+    /// zero area. Triangles with out-of-range indices are not degenerate.
+    fn is_degenerate(&self, tri: &[u32]) -> bool {
+        let vertex_count = self.positions.len() / 3;
+        if tri.iter().any(|&idx| idx as usize >= vertex_count) {
+            return false;
+        }
+
+        if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+            return true;
+        }
+
+        let a = self.vertex_position(tri[0] as usize);
+        let b = self.vertex_position(tri[1] as usize);
+        let c = self.vertex_position(tri[2] as usize);
+        let area = cross(b - a, c - a);
+        area.x == 0.0 && area.y == 0.0 && area.z == 0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::Mesh;
+
+    #[test]
+    fn test_validate_broken() {
+        let mesh = make_broken();
+        let report = mesh.validate();
+
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.layout_errors,
+            vec![
+                LayoutError::AttributeLength {
+                    attribute: "normal",
+                    len: 3,
+                    expected: 18,
+                },
+                LayoutError::IndexOutOfRange {
+                    index_index: 11,
+                    index: 9,
+                    vertex_count: 6,
+                },
+            ]
+        );
+        assert_eq!(report.trailing_indices, 2);
+        assert_eq!(report.non_finite_positions, vec![4]);
+        assert_eq!(report.degenerate_triangles, vec![1]);
+        assert_eq!(report.unreferenced_vertices, vec![5]);
+    }
+
+    #[test]
+    fn test_repair() {
+        let mut mesh = make_broken();
+        let report = mesh.repair();
+
+        assert_eq!(report, make_broken().validate());
+        assert!(mesh.validate().is_valid());
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.triangle_count(), 1);
+        assert_eq!(
+            mesh.positions,
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        );
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_valid() {
+        let mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+
+        let report = mesh.validate();
+        assert!(report.is_valid());
+        assert_eq!(report, ValidationReport::default());
+        assert_eq!(report.to_string(), "Mesh is valid");
+    }
+
+    /// A mesh with one good triangle and one of every problem
+    fn make_broken() -> DeinterleavedIndexedMeshBuf {
+        let nan = ::std::f32::NAN;
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, nan, 0.0, 0.0, 7.0,
+                7.0, 7.0,
+            ],
+            normals: vec![0.0, 0.0, 1.0],
+            // Good, collinear, non-finite, out of range, trailing
+            indices: vec![0, 1, 2, 0, 1, 3, 0, 1, 4, 0, 1, 9, 0, 1],
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use geom::{Normal, Position, Texcoords, Vec2, Vec3, Vertex};
+
+/// A vertex with position, normal, texture coordinates and an optional color,
+/// as yielded by [`vertices`](trait.Mesh.html#tymethod.vertices) of the meshes
+/// in this crate.
+///
+/// Like `aitios_geom::Vertex`, absent normals and texture coordinates are
+/// zero. The color is `None` if the mesh has no colors. Use
+/// [`mesh_vertices`](struct.DeinterleavedIndexedMeshBuf.html#method.mesh_vertices)
+/// to get vertices with only the attributes present in the mesh.
+///
+/// ```
+/// use aitios_scene::{DeinterleavedIndexedMeshBuf, Mesh};
+///
+/// let mesh = DeinterleavedIndexedMeshBuf {
+///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+///     colors: vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0],
+///     indices: vec![0, 1, 2],
+///     ..Default::default()
+/// };
+///
+/// let green = mesh.vertices().nth(1).unwrap();
+/// assert_eq!(green.color(), Some([0.0, 1.0, 0.0, 1.0]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColoredVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub texcoords: Vec2,
+    /// Optional RGBA color, usually in the range from zero to one.
+    pub color: Option<[f32; 4]>,
+}
+
+impl ColoredVertex {
+    /// Gets the RGBA color of the vertex, or `None` if the mesh has no colors.
+    pub fn color(&self) -> Option<[f32; 4]> {
+        self.color
+    }
+}
+
+impl Position for ColoredVertex {
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+}
+
+impl Normal for ColoredVertex {
+    fn normal(&self) -> Vec3 {
+        self.normal
+    }
+}
+
+impl Texcoords for ColoredVertex {
+    fn texcoords(&self) -> Vec2 {
+        self.texcoords
+    }
+}
+
+impl From<Vertex> for ColoredVertex {
+    fn from(vertex: Vertex) -> Self {
+        ColoredVertex {
+            position: vertex.position,
+            normal: vertex.normal,
+            texcoords: vertex.texcoords,
+            color: None,
+        }
+    }
+}
+
+/// Drops the color.
+impl From<ColoredVertex> for Vertex {
+    fn from(vertex: ColoredVertex) -> Self {
+        Vertex {
+            position: vertex.position,
+            normal: vertex.normal,
+            texcoords: vertex.texcoords,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use entity::Entity;
+    use material::MaterialBuilder;
+    use mesh::{DeinterleavedIndexedMeshBuf, IndexedMesh, InterleavedIndexedMeshBuf, Mesh};
+    use std::rc::Rc;
+    use transform::Transform;
+
+    #[test]
+    fn test_colors_on_all_meshes() {
+        let mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            colors: vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.5],
+            indices: vec![2, 1, 0],
+            ..Default::default()
+        };
+        let expected: Vec<_> = (0..3).map(|idx| mesh.color_at(idx)).collect();
+        let colors = |vertices: Vec<ColoredVertex>| -> Vec<_> {
+            vertices.iter().map(|v| v.color()).collect()
+        };
+
+        assert_eq!(expected[0], Some([0.0, 0.0, 1.0, 0.5]));
+        assert_eq!(colors(mesh.vertices().collect()), expected);
+        assert_eq!(colors(mesh.as_slice().vertices().collect()), expected);
+
+        let interleaved = InterleavedIndexedMeshBuf::new(&mesh).unwrap();
+        assert_eq!(colors(interleaved.vertices().collect()), expected);
+
+        let entity = Entity::new(mesh, "colored", Rc::new(MaterialBuilder::new().build()))
+            .with_transform(Transform::from_uniform_scale(2.0));
+        assert_eq!(colors(entity.vertices().collect()), expected);
+        assert_eq!(entity.triangle_at(0).0.color(), expected[0]);
+
+        let plain = DeinterleavedIndexedMeshBuf {
+            colors: Vec::new(),
+            ..entity.bake_transform()
+        };
+        assert!(plain.vertices().all(|v| v.color().is_none()));
+    }
+}
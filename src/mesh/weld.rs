@@ -0,0 +1,375 @@
+use super::deinterleaved::DeinterleavedIndexedMeshBuf;
+use super::layout::MeshVertex;
+use geom::Vec3;
+use std::collections::HashMap;
+
+/// How the values of an attribute are compared when welding vertices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeldTolerance {
+    /// Values must be bitwise identical.
+    Exact,
+    /// Each component may differ by at most the given positive epsilon.
+    /// Welders treat an epsilon that is not positive like `Exact`.
+    Epsilon(f32),
+    /// The attribute is not compared. Welded vertices keep the value of the
+    /// vertex that occurred first.
+    Ignore,
+}
+
+/// Merges vertices with equal or nearly equal attributes into one, rebuilding
+/// a compact indexed mesh.
+///
+/// By default, all attributes must be bitwise identical for vertices to be
+/// welded. Tolerances can be set for all attributes at once or per attribute.
+/// Absent attributes compare like zeros, and integer custom attributes are
+/// always compared exactly.
+///
+/// ```
+/// use aitios_scene::{DeinterleavedIndexedMeshBuf, Mesh, WeldTolerance, Welder};
+///
+/// let mesh = DeinterleavedIndexedMeshBuf {
+///     positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+///     normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+///     texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+///     indices: vec![0, 1, 2, 2, 1, 3],
+///     ..Default::default()
+/// };
+///
+/// // Collecting from vertices on its own yields an unindexed mesh
+/// let unindexed: DeinterleavedIndexedMeshBuf = mesh.vertices().collect();
+/// assert_eq!(unindexed.positions.len(), 6 * 3);
+///
+/// // Welding while collecting keeps the mesh compact
+/// let welded = Welder::new()
+///     .tolerance(WeldTolerance::Epsilon(1e-5))
+///     .collect(mesh.vertices());
+/// assert_eq!(welded.positions.len(), 4 * 3);
+/// assert_eq!(welded.indices, mesh.indices);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Welder {
+    positions: WeldTolerance,
+    normals: WeldTolerance,
+    texcoords: WeldTolerance,
+    tangents: WeldTolerance,
+    colors: WeldTolerance,
+    custom_attributes: WeldTolerance,
+}
+
+impl Welder {
+    /// Creates a welder that only merges vertices with identical attributes.
+    pub fn new() -> Self {
+        Welder {
+            positions: WeldTolerance::Exact,
+            normals: WeldTolerance::Exact,
+            texcoords: WeldTolerance::Exact,
+            tangents: WeldTolerance::Exact,
+            colors: WeldTolerance::Exact,
+            custom_attributes: WeldTolerance::Exact,
+        }
+    }
+
+    /// Sets the tolerance for all attributes.
+    pub fn tolerance(self, tolerance: WeldTolerance) -> Self {
+        let tolerance = tolerance.sanitized();
+        Welder {
+            positions: tolerance,
+            normals: tolerance,
+            texcoords: tolerance,
+            tangents: tolerance,
+            colors: tolerance,
+            custom_attributes: tolerance,
+        }
+    }
+
+    /// Sets the tolerance for positions.
+    ///
+    /// Only vertices with close positions are compared with each other, so
+    /// ignoring positions, or an epsilon in the order of the size of the mesh,
+    /// compares each vertex with all unique vertices found so far and takes
+    /// quadratic time.
+    pub fn position_tolerance(mut self, tolerance: WeldTolerance) -> Self {
+        self.positions = tolerance.sanitized();
+        self
+    }
+
+    /// Sets the tolerance for normals. Ignoring normals welds vertices along
+    /// hard edges, keeping the normal of one side.
+    pub fn normal_tolerance(mut self, tolerance: WeldTolerance) -> Self {
+        self.normals = tolerance.sanitized();
+        self
+    }
+
+    /// Sets the tolerance for all texture coordinate sets.
+    pub fn texcoord_tolerance(mut self, tolerance: WeldTolerance) -> Self {
+        self.texcoords = tolerance.sanitized();
+        self
+    }
+
+    /// Sets the tolerance for tangents and bitangent signs.
+    pub fn tangent_tolerance(mut self, tolerance: WeldTolerance) -> Self {
+        self.tangents = tolerance.sanitized();
+        self
+    }
+
+    /// Sets the tolerance for vertex colors.
+    pub fn color_tolerance(mut self, tolerance: WeldTolerance) -> Self {
+        self.colors = tolerance.sanitized();
+        self
+    }
+
+    /// Sets the tolerance for floating point custom attributes.
+    pub fn custom_tolerance(mut self, tolerance: WeldTolerance) -> Self {
+        self.custom_attributes = tolerance.sanitized();
+        self
+    }
+
+    /// Welds the vertices of the given mesh in place.
+    ///
+    /// The triangles stay the same, but vertices that are not referenced by any
+    /// index are removed and the remaining ones are ordered by first reference.
+    pub fn weld(&self, mesh: &mut DeinterleavedIndexedMeshBuf) {
+        *mesh = self.collect(mesh.mesh_vertices());
+    }
+
+    /// Collects vertices into an indexed mesh, where each vertex is only
+    /// stored once and further occurrences only add an index.
+    ///
+    /// Like the unindexed collection into a mesh, the layout of the mesh is the
+    /// union of the layouts of all vertices.
+    pub fn collect<I, V>(&self, vertices: I) -> DeinterleavedIndexedMeshBuf
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<MeshVertex>,
+    {
+        let mut unique: Vec<MeshVertex> = Vec::new();
+        let mut indices = Vec::new();
+        // Spatial hash of the unique vertices, so only close vertices are compared
+        let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+
+        for vertex in vertices {
+            let vertex: MeshVertex = vertex.into();
+            let cell = self.cell(vertex.position);
+
+            let (neighbors, neighbor_count) = self.neighbor_cells(cell);
+            let found = neighbors[..neighbor_count]
+                .iter()
+                .filter_map(|c| cells.get(c))
+                .flat_map(|candidates| candidates.iter())
+                .find(|&&idx| self.matches(&unique[idx as usize], &vertex))
+                .cloned();
+
+            let idx = match found {
+                Some(idx) => idx,
+                None => {
+                    let idx = unique.len() as u32;
+                    unique.push(vertex);
+                    cells.entry(cell).or_default().push(idx);
+                    idx
+                }
+            };
+
+            indices.push(idx);
+        }
+
+        let mut mesh: DeinterleavedIndexedMeshBuf = unique.into_iter().collect();
+        mesh.indices = indices;
+        mesh
+    }
+
+    fn cell(&self, position: Vec3) -> [i64; 3] {
+        match self.positions {
+            WeldTolerance::Exact => [
+                i64::from(position.x.to_bits()),
+                i64::from(position.y.to_bits()),
+                i64::from(position.z.to_bits()),
+            ],
+            WeldTolerance::Epsilon(epsilon) => [
+                (position.x / epsilon).floor() as i64,
+                (position.y / epsilon).floor() as i64,
+                (position.z / epsilon).floor() as i64,
+            ],
+            WeldTolerance::Ignore => [0; 3],
+        }
+    }
+
+    /// Gets the cells that may contain vertices matching a vertex in the given
+    /// cell, along with how many of the returned cells are used.
+    fn neighbor_cells(&self, cell: [i64; 3]) -> ([[i64; 3]; 27], usize) {
+        let mut neighbors = [cell; 27];
+        match self.positions {
+            WeldTolerance::Epsilon(_) => {
+                for (neighbor_idx, neighbor) in neighbors.iter_mut().enumerate() {
+                    let offset = [neighbor_idx / 9, neighbor_idx / 3 % 3, neighbor_idx % 3];
+                    for (coord, &offset) in neighbor.iter_mut().zip(&offset) {
+                        *coord = coord.saturating_add(offset as i64 - 1);
+                    }
+                }
+                (neighbors, 27)
+            }
+            _ => (neighbors, 1),
+        }
+    }
+
+    fn matches(&self, a: &MeshVertex, b: &MeshVertex) -> bool {
+        let position = |v: &MeshVertex| [v.position.x, v.position.y, v.position.z];
+        let normal = |v: &MeshVertex| v.normal.map_or([0.0; 3], |n| [n.x, n.y, n.z]);
+        let texcoords = |v: &MeshVertex| v.texcoords.map_or([0.0; 2], |t| [t.x, t.y]);
+        let tangent = |v: &MeshVertex| v.tangent.map_or([0.0; 4], |(t, w)| [t.x, t.y, t.z, w]);
+        let color = |v: &MeshVertex| v.color.unwrap_or([0.0; 4]);
+
+        values_match(&position(a), &position(b), self.positions)
+            && values_match(&normal(a), &normal(b), self.normals)
+            && values_match(&texcoords(a), &texcoords(b), self.texcoords)
+            && self.extra_texcoords_match(a, b)
+            && values_match(&tangent(a), &tangent(b), self.tangents)
+            && values_match(&color(a), &color(b), self.colors)
+            && self.custom_attributes_match(a, b)
+    }
+
+    fn extra_texcoords_match(&self, a: &MeshVertex, b: &MeshVertex) -> bool {
+        let set_count = a.extra_texcoords.len().max(b.extra_texcoords.len());
+        (0..set_count).all(|set| {
+            let texcoords =
+                |v: &MeshVertex| v.extra_texcoords.get(set).map_or([0.0; 2], |t| [t.x, t.y]);
+            values_match(&texcoords(a), &texcoords(b), self.texcoords)
+        })
+    }
+
+    fn custom_attributes_match(&self, a: &MeshVertex, b: &MeshVertex) -> bool {
+        if self.custom_attributes == WeldTolerance::Ignore {
+            return true;
+        }
+
+        a.custom.len() == b.custom.len()
+            && a.custom.iter().zip(&b.custom).all(|(a, b)| {
+                a.0 == b.0
+                    && match (a.1.as_f32(), b.1.as_f32()) {
+                        (Some(a), Some(b)) => {
+                            a.len() == b.len() && values_match(a, b, self.custom_attributes)
+                        }
+                        _ => a.1 == b.1,
+                    }
+            })
+    }
+}
+
+impl WeldTolerance {
+    /// Replaces an epsilon that is not positive, including NaN, with `Exact`,
+    /// since it would not match anything but identical values anyway and
+    /// breaks the spatial hash.
+    fn sanitized(self) -> Self {
+        match self {
+            WeldTolerance::Epsilon(epsilon) if epsilon.is_nan() || epsilon <= 0.0 => {
+                WeldTolerance::Exact
+            }
+            tolerance => tolerance,
+        }
+    }
+}
+
+impl Default for Welder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn values_match(a: &[f32], b: &[f32], tolerance: WeldTolerance) -> bool {
+    match tolerance {
+        WeldTolerance::Exact => a.iter().zip(b).all(|(a, b)| a.to_bits() == b.to_bits()),
+        WeldTolerance::Epsilon(epsilon) => a.iter().zip(b).all(|(a, b)| (a - b).abs() <= epsilon),
+        WeldTolerance::Ignore => true,
+    }
+}
+
+impl DeinterleavedIndexedMeshBuf {
+    /// Merges vertices with bitwise identical attributes and removes
+    /// unreferenced vertices, see [`Welder`](struct.Welder.html) for
+    /// welding with tolerances.
+    pub fn deduplicate_vertices(&mut self) {
+        Welder::new().weld(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::{Mesh, NormalWeighting};
+
+    #[test]
+    fn test_deduplicate_collected() {
+        let mesh = make_mesh();
+        let mut unindexed: DeinterleavedIndexedMeshBuf = mesh.mesh_vertices().collect();
+        assert_eq!(unindexed.positions.len(), 6 * 3);
+
+        unindexed.deduplicate_vertices();
+
+        assert_eq!(unindexed.positions, mesh.positions);
+        assert_eq!(unindexed.indices, mesh.indices);
+    }
+
+    #[test]
+    fn test_epsilon() {
+        let mut unindexed: DeinterleavedIndexedMeshBuf = make_mesh().mesh_vertices().collect();
+        let mut far = unindexed.clone();
+        // Noise from a lossy export on the second occurrence of vertex 1
+        unindexed.positions[3 * 3] += 1e-6;
+        far.positions[3 * 3] += 1e-2;
+
+        let mut exact = unindexed.clone();
+        exact.deduplicate_vertices();
+        assert_eq!(exact.positions.len(), 5 * 3);
+
+        let welder = Welder::new().position_tolerance(WeldTolerance::Epsilon(1e-4));
+
+        welder.weld(&mut unindexed);
+        assert_eq!(unindexed.positions.len(), 4 * 3);
+        assert_eq!(unindexed.indices, vec![0, 1, 2, 1, 2, 3]);
+
+        welder.weld(&mut far);
+        assert_eq!(far.positions.len(), 5 * 3);
+    }
+
+    #[test]
+    fn test_degenerate_epsilon() {
+        let zero = Welder::new().tolerance(WeldTolerance::Epsilon(0.0));
+        assert_eq!(zero, Welder::new());
+
+        // Tiny compared to the coordinates, so cells saturate
+        let tiny = Welder::new().tolerance(WeldTolerance::Epsilon(1e-30));
+        let mut mesh: DeinterleavedIndexedMeshBuf = make_mesh().mesh_vertices().collect();
+        mesh.positions[0] = 1e10;
+        tiny.weld(&mut mesh);
+        assert_eq!(
+            mesh.positions,
+            vec![1e10, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0]
+        );
+        assert_eq!(mesh.indices, vec![0, 1, 2, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_ignore_normals() {
+        let mut mesh = make_mesh();
+        mesh.generate_normals(NormalWeighting::Flat);
+        assert_eq!(mesh.positions.len(), 6 * 3);
+
+        let mut welded = mesh.clone();
+        welded.deduplicate_vertices();
+        assert_eq!(welded.positions.len(), 6 * 3);
+
+        Welder::new()
+            .normal_tolerance(WeldTolerance::Ignore)
+            .weld(&mut welded);
+        assert_eq!(welded.positions.len(), 4 * 3);
+        assert_eq!(welded.triangle_count(), 2);
+    }
+
+    /// Two triangles sharing an edge, with a crease along the edge
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0],
+            indices: vec![0, 1, 2, 1, 2, 3],
+            ..Default::default()
+        }
+    }
+}
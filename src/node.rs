@@ -0,0 +1,344 @@
+use entity::Entity;
+use material::{Material, MaterialBuilder};
+use mesh::DeinterleavedIndexedMeshBuf;
+use std::rc::Rc;
+use transform::Transform;
+
+/// A node in a scene graph with a transform relative to its parent, an
+/// optional mesh and material, and any number of child nodes.
+///
+/// The world transform of a node is the product of the local transforms of
+/// all its ancestors and itself, so moving a node also moves all its children.
+///
+/// ```
+/// # extern crate aitios_geom;
+/// # extern crate aitios_scene;
+/// use aitios_scene::{DeinterleavedIndexedMeshBuf, MaterialBuilder, Node, Transform};
+/// use aitios_geom::Vec3;
+/// use std::rc::Rc;
+///
+/// # fn main() {
+/// let stone = Rc::new(MaterialBuilder::new().name("Stone").build());
+/// let house = Node::new("House")
+///     .with_transform(Transform::from_translation(Vec3::new(10.0, 0.0, 0.0)))
+///     .with_mesh(DeinterleavedIndexedMeshBuf::default(), stone)
+///     .with_child(
+///         Node::new("Door")
+///             .with_transform(Transform::from_translation(Vec3::new(0.0, 0.0, 1.0)))
+///             .with_mesh(DeinterleavedIndexedMeshBuf::default(), None),
+///     );
+///
+/// let entities = house.flatten();
+/// assert_eq!(entities.len(), 2);
+/// // The door inherits both transform and material of the house
+/// assert_eq!(
+///     entities[1].transform.translation(),
+///     Vec3::new(10.0, 0.0, 1.0)
+/// );
+/// assert_eq!(entities[1].material.name(), "Stone");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Node {
+    pub name: String,
+    /// Transforms from the space of this node into the space of the parent node.
+    pub transform: Transform,
+    /// Geometry of the node in its own space, if any.
+    pub mesh: Option<Rc<DeinterleavedIndexedMeshBuf>>,
+    /// Material of the mesh. If `None`, the material of the nearest ancestor
+    /// with a material is used.
+    pub material: Option<Rc<Material>>,
+    pub children: Vec<Node>,
+}
+
+/// Visits nodes of a scene graph in depth-first order, see
+/// [`Node::accept`](struct.Node.html#method.accept).
+pub trait NodeVisitor {
+    /// Called when a node is entered, before any of its children, with the
+    /// world transform of the node. Returns whether to descend into the children.
+    fn enter(&mut self, node: &Node, world_transform: &Transform) -> bool;
+
+    /// Called after the node and all its children have been visited.
+    fn leave(&mut self, _node: &Node) {}
+}
+
+impl Node {
+    /// Creates a node without geometry, children and an identity transform.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Node {
+            name: name.into(),
+            transform: Transform::identity(),
+            mesh: None,
+            material: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_mesh<M>(mut self, mesh: DeinterleavedIndexedMeshBuf, material: M) -> Self
+    where
+        M: Into<Option<Rc<Material>>>,
+    {
+        self.mesh = Some(Rc::new(mesh));
+        self.material = material.into();
+        self
+    }
+
+    /// Like [`with_mesh`](#method.with_mesh), but shares an existing mesh
+    /// so it can be instanced in multiple nodes without copying.
+    pub fn with_shared_mesh<M>(mut self, mesh: Rc<DeinterleavedIndexedMeshBuf>, material: M) -> Self
+    where
+        M: Into<Option<Rc<Material>>>,
+    {
+        self.mesh = Some(mesh);
+        self.material = material.into();
+        self
+    }
+
+    pub fn with_child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn add_child(&mut self, child: Node) {
+        self.children.push(child);
+    }
+
+    /// Finds the first node with the given name in depth-first order,
+    /// including this node.
+    pub fn find(&self, name: &str) -> Option<&Node> {
+        if self.name == name {
+            Some(self)
+        } else {
+            self.children.iter().filter_map(|c| c.find(name)).next()
+        }
+    }
+
+    /// Finds the first node with the given name in depth-first order for mutation.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+        if self.name == name {
+            Some(self)
+        } else {
+            self.children
+                .iter_mut()
+                .filter_map(|c| c.find_mut(name))
+                .next()
+        }
+    }
+
+    /// Visits this node and its descendants in depth-first order, treating
+    /// this node as the root of the scene graph.
+    pub fn accept<V: NodeVisitor>(&self, visitor: &mut V) {
+        self.accept_with_parent(visitor, &Transform::identity());
+    }
+
+    /// Calls the given function for each node in depth-first order, with the
+    /// world transform of the node.
+    pub fn for_each<F>(&self, func: F)
+    where
+        F: FnMut(&Node, &Transform),
+    {
+        struct FnVisitor<F>(F);
+
+        impl<F> NodeVisitor for FnVisitor<F>
+        where
+            F: FnMut(&Node, &Transform),
+        {
+            fn enter(&mut self, node: &Node, world_transform: &Transform) -> bool {
+                (self.0)(node, world_transform);
+                true
+            }
+        }
+
+        self.accept(&mut FnVisitor(func));
+    }
+
+    /// Converts every node with a mesh into an entity with the world transform
+    /// of the node. The meshes are shared with the scene graph, not copied.
+    ///
+    /// Meshes without a material in themselves or any ancestor are given a
+    /// shared, empty material.
+    pub fn flatten(&self) -> Vec<Entity> {
+        struct Flattener {
+            entities: Vec<Entity>,
+            materials: Vec<Rc<Material>>,
+        }
+
+        impl NodeVisitor for Flattener {
+            fn enter(&mut self, node: &Node, world_transform: &Transform) -> bool {
+                let material = match node.material {
+                    Some(ref material) => Rc::clone(material),
+                    None => Rc::clone(self.materials.last().unwrap()),
+                };
+
+                if let Some(ref mesh) = node.mesh {
+                    self.entities.push(Entity {
+                        name: node.name.clone(),
+                        material: Rc::clone(&material),
+                        mesh: Rc::clone(mesh),
+                        transform: *world_transform,
+                    });
+                }
+
+                self.materials.push(material);
+                true
+            }
+
+            fn leave(&mut self, _node: &Node) {
+                self.materials.pop();
+            }
+        }
+
+        let mut flattener = Flattener {
+            entities: Vec::new(),
+            materials: vec![Rc::new(MaterialBuilder::new().build())],
+        };
+        self.accept(&mut flattener);
+        flattener.entities
+    }
+
+    fn accept_with_parent<V: NodeVisitor>(&self, visitor: &mut V, parent_world: &Transform) {
+        let world = *parent_world * self.transform;
+
+        if visitor.enter(self, &world) {
+            for child in &self.children {
+                child.accept_with_parent(visitor, &world);
+            }
+        }
+
+        visitor.leave(self);
+    }
+}
+
+/// Creates a leaf node with the mesh, material and transform of the entity.
+impl From<Entity> for Node {
+    fn from(entity: Entity) -> Self {
+        Node {
+            name: entity.name,
+            transform: entity.transform,
+            mesh: Some(entity.mesh),
+            material: Some(entity.material),
+            children: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::Vec3;
+
+    #[test]
+    fn test_world_transforms() {
+        let graph = make_graph();
+        let mut visited = Vec::new();
+
+        graph.for_each(|node, world| {
+            visited.push((
+                node.name.clone(),
+                world.transform_point(Vec3::new(0.0, 0.0, 0.0)),
+            ))
+        });
+
+        let names: Vec<&str> = visited.iter().map(|v| v.0.as_str()).collect();
+        assert_eq!(names, vec!["Street", "House", "Door", "Window", "Tree"]);
+        assert_eq!(visited[2].1, Vec3::new(20.0, 0.0, 1.0));
+        assert_eq!(visited[3].1, Vec3::new(10.0, 5.0, 0.0));
+        assert_eq!(visited[4].1, Vec3::new(0.0, 0.0, 3.0));
+    }
+
+    #[test]
+    fn test_visitor_skips_children() {
+        struct Counter {
+            entered: usize,
+            left: usize,
+        }
+
+        impl NodeVisitor for Counter {
+            fn enter(&mut self, node: &Node, _world: &Transform) -> bool {
+                self.entered += 1;
+                node.name != "House"
+            }
+
+            fn leave(&mut self, _node: &Node) {
+                self.left += 1;
+            }
+        }
+
+        let mut counter = Counter {
+            entered: 0,
+            left: 0,
+        };
+        make_graph().accept(&mut counter);
+
+        assert_eq!(counter.entered, 3);
+        assert_eq!(counter.left, 3);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let graph = make_graph();
+        let entities = graph.flatten();
+
+        let names: Vec<&str> = entities.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["House", "Door", "Window", "Tree"]);
+
+        // Door and window inherit the material of the house
+        assert_eq!(entities[1].material.name(), "Brick");
+        assert!(Rc::ptr_eq(&entities[0].material, &entities[2].material));
+        assert_eq!(entities[3].material.name(), "");
+
+        // Geometry is shared with the graph
+        let house_mesh = graph.find("House").unwrap().mesh.as_ref().unwrap();
+        assert!(Rc::ptr_eq(&entities[0].mesh, house_mesh));
+
+        assert_eq!(
+            entities[1].transform.translation(),
+            Vec3::new(20.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let mut graph = make_graph();
+
+        assert!(graph.find("Window").is_some());
+        assert!(graph.find("Chimney").is_none());
+
+        graph.find_mut("Door").unwrap().add_child(Node::new("Knob"));
+        assert_eq!(graph.find("Knob").unwrap().name, "Knob");
+    }
+
+    fn make_graph() -> Node {
+        let brick = Rc::new(MaterialBuilder::new().name("Brick").build());
+
+        Node::new("Street")
+            .with_child(
+                Node::new("House")
+                    .with_transform(
+                        Transform::from_translation(Vec3::new(10.0, 0.0, 0.0))
+                            * Transform::from_uniform_scale(2.0),
+                    )
+                    .with_mesh(DeinterleavedIndexedMeshBuf::default(), brick)
+                    .with_child(
+                        Node::new("Door")
+                            .with_transform(Transform::from_translation(Vec3::new(5.0, 0.0, 0.5)))
+                            .with_mesh(DeinterleavedIndexedMeshBuf::default(), None),
+                    )
+                    .with_child(
+                        Node::new("Window")
+                            .with_transform(Transform::from_translation(Vec3::new(0.0, 2.5, 0.0)))
+                            .with_mesh(DeinterleavedIndexedMeshBuf::default(), None),
+                    ),
+            )
+            .with_child(
+                Node::new("Tree")
+                    .with_transform(Transform::from_translation(Vec3::new(0.0, 0.0, 3.0)))
+                    .with_mesh(DeinterleavedIndexedMeshBuf::default(), None),
+            )
+    }
+}
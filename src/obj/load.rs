@@ -0,0 +1,689 @@
+use entity::Entity;
+use material::{Material, MaterialBuilder};
+use mesh::{DeinterleavedIndexedMeshBuf, NormalWeighting};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tobj;
+
+type MapSetter = fn(MaterialBuilder, PathBuf) -> MaterialBuilder;
+
+/// Maps MTL keys that tobj does not know about and stores in `unknown_param`
+/// against the corresponding setters on `MaterialBuilder`.
+const UNKNOWN_PARAM_MAPS: &[(&str, MapSetter)] = &[
+    ("bump", MaterialBuilder::bump_map::<PathBuf>),
+    ("map_bump", MaterialBuilder::bump_map::<PathBuf>),
+    ("map_Bump", MaterialBuilder::bump_map::<PathBuf>),
+    ("disp", MaterialBuilder::displacement_map::<PathBuf>),
+    ("norm", MaterialBuilder::normal_map::<PathBuf>),
+    ("map_Pr", MaterialBuilder::roughness_map::<PathBuf>),
+    ("map_Pm", MaterialBuilder::metallic_map::<PathBuf>),
+    ("map_Ps", MaterialBuilder::sheen_map::<PathBuf>),
+    ("map_Ke", MaterialBuilder::emissive_map::<PathBuf>),
+];
+
+/// Errors that can occur while loading entities from OBJ files.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The OBJ file or one of its MTL libraries could not be read.
+    Io { path: PathBuf, cause: io::Error },
+    /// The OBJ or MTL data was malformed and tobj refused to parse it.
+    Parse(tobj::LoadError),
+    /// A vertex attribute of a model had a length incompatible with its positions.
+    AttributeLength {
+        model: String,
+        attribute: &'static str,
+        len: usize,
+        expected: usize,
+    },
+    /// A model had an index that referenced a vertex that does not exist.
+    IndexOutOfRange {
+        model: String,
+        index: u32,
+        vertex_count: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io {
+                ref path,
+                ref cause,
+            } => write!(f, "Could not read {}: {}", path.display(), cause),
+            LoadError::Parse(ref cause) => write!(f, "Malformed OBJ or MTL data: {}", cause),
+            LoadError::AttributeLength {
+                ref model,
+                attribute,
+                len,
+                expected,
+            } => write!(
+                f,
+                "Model {} has {} {} values, expected {}",
+                model, len, attribute, expected
+            ),
+            LoadError::IndexOutOfRange {
+                ref model,
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "Model {} references vertex {} but only has {} vertices",
+                model, index, vertex_count
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LoadError::Io { ref cause, .. } => Some(cause),
+            LoadError::Parse(ref cause) => Some(cause),
+            _ => None,
+        }
+    }
+}
+
+impl From<tobj::LoadError> for LoadError {
+    fn from(err: tobj::LoadError) -> Self {
+        LoadError::Parse(err)
+    }
+}
+
+/// Loads entities from OBJ files, with configurable handling of missing data.
+///
+/// ```no_run
+/// use aitios_scene::{NormalWeighting, ObjLoader};
+/// use std::f32::consts::PI;
+///
+/// let entities = ObjLoader::new()
+///     .generate_missing_normals(NormalWeighting::Angle)
+///     .crease_angle(0.25 * PI)
+///     .load("/tmp/photogrammetry.obj")
+///     .unwrap();
+/// ```
+pub struct ObjLoader {
+    missing_normals: Option<NormalWeighting>,
+    crease_angle: Option<f32>,
+}
+
+impl ObjLoader {
+    /// Creates a loader that keeps models without normals as they are.
+    pub fn new() -> Self {
+        ObjLoader {
+            missing_normals: None,
+            crease_angle: None,
+        }
+    }
+
+    /// Generates normals with the given weighting for models without normals,
+    /// instead of loading them without normals.
+    pub fn generate_missing_normals(mut self, weighting: NormalWeighting) -> Self {
+        self.missing_normals = Some(weighting);
+        self
+    }
+
+    /// Sets a crease angle in radians for generated normals, so that edges
+    /// sharper than the angle stay hard. By default, all edges are smoothed.
+    pub fn crease_angle(mut self, radians: f32) -> Self {
+        self.crease_angle = Some(radians);
+        self
+    }
+
+    /// Loads an OBJ file and its MTL libraries into a vector of entities.
+    ///
+    /// Each model in the OBJ file becomes one entity with its own mesh. Entities
+    /// with the same material share a reference to it, entities without a material
+    /// share a reference to an empty, unnamed material.
+    ///
+    /// Texture map paths in MTL files are resolved relative to the directory of the
+    /// MTL file they were declared in. If a texture map declaration has options, the
+    /// last whitespace-separated token is taken to be the path.
+    ///
+    /// Normals and texture coordinates are optional, models without them
+    /// get meshes with empty normal or texture coordinate vectors.
+    ///
+    /// Vertex colors in the `v x y z r g b` extension of OBJ are loaded into the
+    /// mesh colors, with an optional seventh value for alpha. Each vertex gets
+    /// the color of the `v` record its face corners refer to. Vertices without
+    /// a color in models that have colors on other vertices are white.
+    pub fn load<P: AsRef<Path>>(&self, obj_path: P) -> Result<Vec<Entity>, LoadError> {
+        let obj_path = obj_path.as_ref();
+        let scan = scan_obj(obj_path)?;
+        let (models, materials) = tobj::load_obj(obj_path)?;
+
+        entities_from_tobj(models, &materials, &scan, self)
+    }
+}
+
+impl Default for ObjLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Loads an OBJ file and its MTL libraries into a vector of entities, using
+/// the default settings of [`ObjLoader`](struct.ObjLoader.html).
+///
+/// Models without normals or texture coordinates are loaded without them.
+///
+/// ```no_run
+/// use aitios_scene::load_obj;
+///
+/// let entities = load_obj("/tmp/buildings.obj").unwrap();
+/// for entity in entities {
+///     println!("{}: {:?}", entity.name, entity.material.diffuse_color_map());
+/// }
+/// ```
+pub fn load_obj<P: AsRef<Path>>(obj_path: P) -> Result<Vec<Entity>, LoadError> {
+    ObjLoader::new().load(obj_path)
+}
+
+fn entities_from_tobj(
+    models: Vec<tobj::Model>,
+    materials: &[tobj::Material],
+    scan: &ObjScan,
+    loader: &ObjLoader,
+) -> Result<Vec<Entity>, LoadError> {
+    let materials: Vec<Rc<Material>> = materials
+        .iter()
+        .map(|m| {
+            let dir = scan
+                .material_dirs
+                .get(&m.name)
+                .map(|d| d.as_path())
+                .unwrap_or_else(|| Path::new(""));
+            Rc::new(convert_material(m, dir))
+        })
+        .collect();
+
+    let fallback_material = Rc::new(MaterialBuilder::new().build());
+
+    models
+        .into_iter()
+        .enumerate()
+        .map(|(model_idx, model)| {
+            let material = model
+                .mesh
+                .material_id
+                .and_then(|id| materials.get(id))
+                .unwrap_or(&fallback_material);
+            let material = Rc::clone(material);
+            let colors = scan.model_colors(model_idx, model.mesh.positions.len() / 3);
+            let mesh = convert_mesh(&model.name, model.mesh, colors, loader)?;
+
+            Ok(Entity::new(mesh, model.name, material))
+        })
+        .collect()
+}
+
+fn convert_mesh(
+    model: &str,
+    mesh: tobj::Mesh,
+    colors: Vec<f32>,
+    loader: &ObjLoader,
+) -> Result<DeinterleavedIndexedMeshBuf, LoadError> {
+    let tobj::Mesh {
+        positions,
+        normals,
+        texcoords,
+        indices,
+        ..
+    } = mesh;
+
+    let attribute_error = |attribute, len, expected| LoadError::AttributeLength {
+        model: model.to_string(),
+        attribute,
+        len,
+        expected,
+    };
+
+    if positions.len() % 3 != 0 {
+        let expected = positions.len() - positions.len() % 3;
+        return Err(attribute_error("position", positions.len(), expected));
+    }
+
+    let vertex_count = positions.len() / 3;
+
+    if !normals.is_empty() && normals.len() != positions.len() {
+        return Err(attribute_error("normal", normals.len(), positions.len()));
+    }
+
+    if !texcoords.is_empty() && texcoords.len() != vertex_count * 2 {
+        return Err(attribute_error(
+            "texcoord",
+            texcoords.len(),
+            vertex_count * 2,
+        ));
+    }
+
+    if indices.len() % 3 != 0 {
+        let expected = indices.len() - indices.len() % 3;
+        return Err(attribute_error("index", indices.len(), expected));
+    }
+
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+        return Err(LoadError::IndexOutOfRange {
+            model: model.to_string(),
+            index,
+            vertex_count,
+        });
+    }
+
+    if !colors.is_empty() && colors.len() != vertex_count * 4 {
+        return Err(attribute_error("color", colors.len(), vertex_count * 4));
+    }
+
+    let mut mesh = DeinterleavedIndexedMeshBuf {
+        positions,
+        normals,
+        texcoords,
+        colors,
+        indices,
+        ..Default::default()
+    };
+
+    if mesh.normals.is_empty() {
+        if let Some(weighting) = loader.missing_normals {
+            match loader.crease_angle {
+                Some(angle) => mesh.generate_normals_with_crease(weighting, angle),
+                None => mesh.generate_normals(weighting),
+            }
+        }
+    }
+
+    Ok(mesh)
+}
+
+fn convert_material(mat: &tobj::Material, mtl_dir: &Path) -> Material {
+    let map = |value: &str| {
+        value
+            .split_whitespace()
+            .last()
+            .map(|path| mtl_dir.join(path))
+    };
+
+    let mut builder = MaterialBuilder::new().name(mat.name.clone());
+
+    if let Some(path) = map(&mat.diffuse_texture) {
+        builder = builder.diffuse_color_map(path);
+    }
+
+    if let Some(path) = map(&mat.ambient_texture) {
+        builder = builder.ambient_color_map(path);
+    }
+
+    if let Some(path) = map(&mat.specular_texture) {
+        builder = builder.specular_color_map(path);
+    }
+
+    for &(key, set_map) in UNKNOWN_PARAM_MAPS {
+        if let Some(path) = mat.unknown_param.get(key).and_then(|v| map(v)) {
+            builder = set_map(builder, path);
+        }
+    }
+
+    builder.build()
+}
+
+/// Information from an OBJ file that tobj does not provide, gathered in a
+/// single pass over the file.
+#[derive(Debug, Default)]
+struct ObjScan {
+    /// Maps the names of the materials declared in the referenced MTL
+    /// libraries against the directories the MTL files are located in.
+    material_dirs: HashMap<String, PathBuf>,
+    /// The RGBA color declared with each `v` record, if any.
+    colors: Vec<Option<[f32; 4]>>,
+    /// The `v` record of each vertex of each model, in the order tobj creates
+    /// models and vertices.
+    model_positions: Vec<Vec<usize>>,
+}
+
+impl ObjScan {
+    /// Gets the colors of the vertices of the model with the given number,
+    /// or an empty vector if none of its vertices has a color.
+    fn model_colors(&self, model_idx: usize, vertex_count: usize) -> Vec<f32> {
+        let positions = match self.model_positions.get(model_idx) {
+            // Only trust the mapping if tobj came to the same vertices
+            Some(positions) if positions.len() == vertex_count => positions,
+            _ => return Vec::new(),
+        };
+
+        let color = |&position: &usize| self.colors.get(position).cloned().unwrap_or(None);
+        if !positions.iter().any(|p| color(p).is_some()) {
+            return Vec::new();
+        }
+
+        let mut colors = Vec::with_capacity(vertex_count * 4);
+        for position in positions {
+            colors.extend_from_slice(&color(position).unwrap_or([1.0; 4]));
+        }
+        colors
+    }
+}
+
+/// Reads the OBJ file at the given path and its MTL libraries to find the
+/// directories of materials and the colors of vertices.
+///
+/// Vertices are numbered like tobj does it: faces are collected into a new
+/// model on every `o`, `g` or `usemtl` that follows faces, and every distinct
+/// combination of `v`, `vt` and `vn` in the faces of a model becomes a new
+/// vertex, in the order of first use.
+fn scan_obj(obj_path: &Path) -> Result<ObjScan, LoadError> {
+    let obj_dir = obj_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut scan = ObjScan::default();
+    let (mut texcoord_count, mut normal_count) = (0, 0);
+    let mut model_vertices = HashSet::new();
+    let mut positions = Vec::new();
+
+    let obj = File::open(obj_path).map_err(|cause| LoadError::Io {
+        path: obj_path.to_path_buf(),
+        cause,
+    })?;
+    for line in BufReader::new(obj).lines() {
+        let line = line.map_err(|cause| LoadError::Io {
+            path: obj_path.to_path_buf(),
+            cause,
+        })?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let values: Vec<f32> = tokens.filter_map(|t| t.parse().ok()).collect();
+                let color = match values.len() {
+                    6 | 7 => {
+                        let alpha = values.get(6).cloned().unwrap_or(1.0);
+                        Some([values[3], values[4], values[5], alpha])
+                    }
+                    _ => None,
+                };
+                scan.colors.push(color);
+            }
+            Some("vt") => texcoord_count += 1,
+            Some("vn") => normal_count += 1,
+            Some("f") => {
+                for corner in tokens {
+                    let mut refs = corner.split('/');
+                    let mut next_ref = |count| {
+                        refs.next()
+                            .and_then(|r| r.parse().ok())
+                            .and_then(|r| resolve_reference(r, count))
+                    };
+                    let key = (
+                        next_ref(scan.colors.len()),
+                        next_ref(texcoord_count),
+                        next_ref(normal_count),
+                    );
+
+                    // Malformed faces are reported by tobj
+                    if let Some(position) = key.0 {
+                        if model_vertices.insert(key) {
+                            positions.push(position);
+                        }
+                    }
+                }
+            }
+            Some("o") | Some("g") | Some("usemtl") => {
+                if !positions.is_empty() {
+                    scan.model_positions.push(positions.split_off(0));
+                    model_vertices.clear();
+                }
+            }
+            Some("mtllib") => {
+                for lib in tokens {
+                    let mtl_path = obj_dir.join(lib);
+                    let mtl_dir = mtl_path
+                        .parent()
+                        .map(|d| d.to_path_buf())
+                        .unwrap_or_default();
+
+                    for line in read_lines(&mtl_path)? {
+                        let mut tokens = line.split_whitespace();
+                        if tokens.next() == Some("newmtl") {
+                            let name = tokens.collect::<Vec<_>>().join(" ");
+                            scan.material_dirs
+                                .entry(name)
+                                .or_insert_with(|| mtl_dir.clone());
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !positions.is_empty() {
+        scan.model_positions.push(positions);
+    }
+
+    Ok(scan)
+}
+
+/// Turns a one-based reference to an OBJ record, or a negative one counting
+/// back from the latest record, into a zero-based index.
+fn resolve_reference(reference: isize, record_count: usize) -> Option<usize> {
+    if reference > 0 {
+        Some(reference as usize - 1)
+    } else if reference < 0 && (-reference) as usize <= record_count {
+        Some(record_count - (-reference) as usize)
+    } else {
+        None
+    }
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, LoadError> {
+    let io_error = |cause| LoadError::Io {
+        path: path.to_path_buf(),
+        cause,
+    };
+
+    let file = File::open(path).map_err(io_error)?;
+    BufReader::new(file)
+        .lines()
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(io_error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mesh::Mesh;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_scan_material_dirs() {
+        let dir = env::temp_dir().join("aitios-scene-test-material-dirs");
+        fs::create_dir_all(dir.join("materials")).unwrap();
+
+        let obj_path = dir.join("scene.obj");
+        File::create(&obj_path)
+            .unwrap()
+            .write_all(b"mtllib materials/scene.mtl\no cube\nv 0 0 0\n")
+            .unwrap();
+        File::create(dir.join("materials/scene.mtl"))
+            .unwrap()
+            .write_all(b"newmtl Rusty Metal\nmap_Kd rust.png\n\nnewmtl Stone\n")
+            .unwrap();
+
+        let dirs = scan_obj(&obj_path).unwrap().material_dirs;
+
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(dirs["Rusty Metal"], dir.join("materials"));
+        assert_eq!(dirs["Stone"], dir.join("materials"));
+    }
+
+    #[test]
+    fn test_vertex_colors() {
+        let dir = env::temp_dir().join("aitios-scene-test-vertex-colors");
+        fs::create_dir_all(&dir).unwrap();
+
+        let obj_path = dir.join("scan.obj");
+        File::create(&obj_path)
+            .unwrap()
+            .write_all(
+                b"o scan\nv 0 0 0 1 0 0\nv 1 0 0\nv 0 1 0 0 0 1 0.5\nf 1 2 3\n\
+                  o seam\nv 0 0 0 0 1 0\nf 4 -3 -2\n",
+            )
+            .unwrap();
+
+        let entities = load_obj(&obj_path).unwrap();
+
+        assert_eq!(
+            entities[0].mesh.colors,
+            vec![1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.5]
+        );
+        assert_eq!(entities[0].mesh.color_at(2), Some([0.0, 0.0, 1.0, 0.5]));
+        // Same position as the first vertex of the first model, other color
+        assert_eq!(entities[1].mesh.color_at(0), Some([0.0, 1.0, 0.0, 1.0]));
+        assert_eq!(entities[1].mesh.color_at(2), Some([0.0, 0.0, 1.0, 0.5]));
+    }
+
+    #[test]
+    fn test_scan_model_positions() {
+        let dir = env::temp_dir().join("aitios-scene-test-scan-model-positions");
+        fs::create_dir_all(&dir).unwrap();
+
+        let obj_path = dir.join("quads.obj");
+        File::create(&obj_path)
+            .unwrap()
+            .write_all(
+                b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\n\
+                  f 1/1 2/1 3/2 4/2\nf 3/2 2/1 1/1\nusemtl other\nf 4/-1 1/-2 1/-1\n",
+            )
+            .unwrap();
+
+        let scan = scan_obj(&obj_path).unwrap();
+
+        assert_eq!(scan.model_positions, vec![vec![0, 1, 2, 3], vec![3, 0, 0]]);
+        assert!(scan.model_colors(0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_missing_obj() {
+        let result = load_obj("/tmp/aitios-scene-this-file-does-not-exist.obj");
+
+        match result {
+            Err(LoadError::Io { .. }) => (),
+            _ => panic!("Expected IO error for missing file"),
+        }
+    }
+
+    #[test]
+    fn test_convert_shares_materials() {
+        let models = vec![
+            make_model("First", Some(0)),
+            make_model("Second", Some(0)),
+            make_model("Third", None),
+        ];
+        let materials = vec![make_material("Stone")];
+        let mut scan = ObjScan::default();
+        scan.material_dirs
+            .insert(String::from("Stone"), PathBuf::from("/tmp/mtl"));
+
+        let entities = entities_from_tobj(models, &materials, &scan, &ObjLoader::new()).unwrap();
+
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0].name, "First");
+        assert_eq!(entities[0].mesh.triangle_count(), 1);
+        assert!(entities[0].mesh.texcoords.is_empty());
+        assert!(Rc::ptr_eq(&entities[0].material, &entities[1].material));
+        assert_eq!(entities[2].material.name(), "");
+
+        let stone = &entities[0].material;
+        assert_eq!(stone.name(), "Stone");
+        assert_eq!(
+            stone.diffuse_color_map(),
+            Some(&PathBuf::from("/tmp/mtl/textures/stone.png"))
+        );
+        assert_eq!(
+            stone.bump_map(),
+            Some(&PathBuf::from("/tmp/mtl/stone_bump.png"))
+        );
+        assert!(stone.specular_color_map().is_none());
+    }
+
+    #[test]
+    fn test_convert_mismatched_attributes() {
+        let mut model = make_model("Broken", None);
+        model.mesh.normals.pop();
+
+        match entities_from_tobj(vec![model], &[], &ObjScan::default(), &ObjLoader::new()) {
+            Err(LoadError::AttributeLength {
+                attribute: "normal",
+                len: 8,
+                expected: 9,
+                ..
+            }) => (),
+            _ => panic!("Expected attribute length error"),
+        }
+
+        let mut model = make_model("Broken", None);
+        model.mesh.indices[2] = 3;
+
+        match entities_from_tobj(vec![model], &[], &ObjScan::default(), &ObjLoader::new()) {
+            Err(LoadError::IndexOutOfRange { index: 3, .. }) => (),
+            _ => panic!("Expected index out of range error"),
+        }
+    }
+
+    #[test]
+    fn test_convert_missing_normals() {
+        let mut model = make_model("Unlit", None);
+        model.mesh.normals.clear();
+
+        let loader = ObjLoader::new();
+        let entities =
+            entities_from_tobj(vec![model.clone()], &[], &ObjScan::default(), &loader).unwrap();
+        assert!(entities[0].mesh.normals.is_empty());
+        assert!(!entities[0].mesh.layout().normals);
+
+        let loader = ObjLoader::new().generate_missing_normals(NormalWeighting::Area);
+        let entities = entities_from_tobj(vec![model], &[], &ObjScan::default(), &loader).unwrap();
+        assert_eq!(
+            entities[0].mesh.normals,
+            vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]
+        );
+    }
+
+    fn make_model(name: &str, material_id: Option<usize>) -> tobj::Model {
+        tobj::Model {
+            name: String::from(name),
+            mesh: tobj::Mesh {
+                positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+                normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+                texcoords: vec![],
+                indices: vec![0, 1, 2],
+                material_id,
+            },
+        }
+    }
+
+    fn make_material(name: &str) -> tobj::Material {
+        let mut unknown_param = HashMap::new();
+        unknown_param.insert(String::from("bump"), String::from("-bm 0.5 stone_bump.png"));
+
+        tobj::Material {
+            name: String::from(name),
+            ambient: [0.0; 3],
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            dissolve: 1.0,
+            optical_density: 1.0,
+            ambient_texture: String::new(),
+            diffuse_texture: String::from("textures/stone.png"),
+            specular_texture: String::new(),
+            normal_texture: String::new(),
+            dissolve_texture: String::new(),
+            illumination_model: None,
+            unknown_param,
+        }
+    }
+}
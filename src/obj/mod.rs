@@ -0,0 +1,5 @@
+mod load;
+mod save;
+
+pub use self::load::{load_obj, LoadError, ObjLoader};
+pub use self::save::{save_obj, ObjExporter};
@@ -0,0 +1,457 @@
+use entity::Entity;
+use geom::Vec3;
+use material::Material;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Writes entities to OBJ files with a companion MTL library.
+///
+/// Materials shared by multiple entities are only written once, both when the
+/// entities share the same reference and when the materials are merely equal.
+/// Materials with clashing names get a numeric suffix to keep them apart.
+///
+/// Geometry is written in world space. Since OBJ has no notion of instancing,
+/// every entity gets its own copy of the vertices in the file, but transforms
+/// are applied while writing so meshes are never duplicated in memory.
+///
+/// Vertex colors are written with the common `v x y z r g b` extension of OBJ,
+/// which has no alpha channel. OBJ only supports a single texture coordinate
+/// set, so additional sets are not written, use
+/// [`save_ply`](fn.save_ply.html) to keep them.
+///
+/// ```no_run
+/// use aitios_scene::{load_obj, ObjExporter};
+///
+/// let entities = load_obj("/tmp/in/buildings.obj").unwrap();
+///
+/// ObjExporter::new()
+///     .relative_texture_paths(true)
+///     .export(&entities, "/tmp/out/buildings.obj")
+///     .unwrap();
+/// ```
+pub struct ObjExporter {
+    mtl_path: Option<PathBuf>,
+    relative_texture_paths: bool,
+}
+
+impl ObjExporter {
+    /// Creates an exporter that writes the MTL library next to the OBJ file
+    /// and leaves texture paths untouched.
+    pub fn new() -> Self {
+        ObjExporter {
+            mtl_path: None,
+            relative_texture_paths: false,
+        }
+    }
+
+    /// Sets the path of the MTL library to write. By default, the path of the
+    /// OBJ file with the extension replaced by `mtl` is used.
+    pub fn mtl_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.mtl_path = Some(path.into());
+        self
+    }
+
+    /// If set, texture paths are rewritten to be relative to the directory of
+    /// the written MTL library, which makes the output directory relocatable.
+    /// Paths that cannot be expressed relative to the directory are kept as is.
+    pub fn relative_texture_paths(mut self, relative: bool) -> Self {
+        self.relative_texture_paths = relative;
+        self
+    }
+
+    /// Writes the given entities to an OBJ file at the given path and their
+    /// materials to an MTL library.
+    pub fn export<P: AsRef<Path>>(&self, entities: &[Entity], obj_path: P) -> io::Result<()> {
+        let obj_path = obj_path.as_ref();
+        let mtl_path = self
+            .mtl_path
+            .clone()
+            .unwrap_or_else(|| obj_path.with_extension("mtl"));
+
+        let obj_dir = obj_path.parent().unwrap_or_else(|| Path::new(""));
+        let mtl_dir = mtl_path.parent().unwrap_or_else(|| Path::new(""));
+        let mtllib = relative_path(obj_dir, &mtl_path).unwrap_or_else(|| mtl_path.clone());
+
+        let materials = MaterialLibrary::new(entities);
+
+        let mut obj = BufWriter::new(File::create(obj_path)?);
+        write_obj(&mut obj, entities, &materials, &mtllib)?;
+        obj.flush()?;
+
+        let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+        let base_dir = if self.relative_texture_paths {
+            Some(mtl_dir)
+        } else {
+            None
+        };
+        write_mtl(&mut mtl, &materials, base_dir)?;
+        mtl.flush()
+    }
+}
+
+impl Default for ObjExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the given entities to an OBJ file at the given path and their
+/// materials to an MTL library next to it, using the default settings of
+/// [`ObjExporter`](struct.ObjExporter.html).
+pub fn save_obj<P: AsRef<Path>>(entities: &[Entity], obj_path: P) -> io::Result<()> {
+    ObjExporter::new().export(entities, obj_path)
+}
+
+/// Deduplicated materials of a set of entities with unique names.
+struct MaterialLibrary<'a> {
+    materials: Vec<(&'a Rc<Material>, String)>,
+}
+
+impl<'a> MaterialLibrary<'a> {
+    fn new(entities: &'a [Entity]) -> Self {
+        let mut materials: Vec<(&'a Rc<Material>, String)> = Vec::new();
+
+        for entity in entities {
+            let material = &entity.material;
+            let known = materials
+                .iter()
+                .any(|&(m, _)| Rc::ptr_eq(m, material) || **m == **material);
+
+            if !known {
+                let base_name = if material.name().is_empty() {
+                    String::from("material")
+                } else {
+                    material.name().replace(char::is_whitespace, "_")
+                };
+
+                let mut name = base_name.clone();
+                let mut suffix = 1;
+                while materials.iter().any(|(_, n)| *n == name) {
+                    name = format!("{}_{}", base_name, suffix);
+                    suffix += 1;
+                }
+
+                materials.push((material, name));
+            }
+        }
+
+        MaterialLibrary { materials }
+    }
+
+    fn name_of(&self, material: &Rc<Material>) -> &str {
+        self.materials
+            .iter()
+            .find(|&&(m, _)| Rc::ptr_eq(m, material) || **m == **material)
+            .map(|(_, name)| name.as_str())
+            .expect("Material of entity missing in library")
+    }
+}
+
+fn write_obj<W: Write>(
+    obj: &mut W,
+    entities: &[Entity],
+    materials: &MaterialLibrary,
+    mtllib: &Path,
+) -> io::Result<()> {
+    writeln!(obj, "mtllib {}", mtllib.display())?;
+
+    // OBJ indices are one-based and global across the file, counted
+    // separately for each kind of record
+    let mut position_offset = 1;
+    let mut texcoord_offset = 1;
+    let mut normal_offset = 1;
+
+    for entity in entities {
+        let mesh = &entity.mesh;
+        let transform = &entity.transform;
+
+        writeln!(obj, "o {}", entity.name.replace(char::is_whitespace, "_"))?;
+
+        // Geometry is written in world space
+        for (vertex_idx, p) in mesh.positions.chunks(3).enumerate() {
+            let p = transform.transform_point(Vec3::new(p[0], p[1], p[2]));
+            if mesh.colors.is_empty() {
+                writeln!(obj, "v {} {} {}", p.x, p.y, p.z)?;
+            } else {
+                let c = mesh.vertex_color(vertex_idx);
+                writeln!(obj, "v {} {} {} {} {} {}", p.x, p.y, p.z, c[0], c[1], c[2])?;
+            }
+        }
+
+        for n in mesh.normals.chunks(3) {
+            let n = transform.transform_normal(Vec3::new(n[0], n[1], n[2]));
+            writeln!(obj, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+
+        for t in mesh.texcoords.chunks(2) {
+            writeln!(obj, "vt {} {}", t[0], t[1])?;
+        }
+
+        writeln!(obj, "usemtl {}", materials.name_of(&entity.material))?;
+
+        let layout = mesh.layout();
+        for tri in mesh.indices.chunks(3).filter(|tri| tri.len() == 3) {
+            write!(obj, "f")?;
+            for &idx in tri {
+                let idx = idx as usize;
+                let (p, t, n) = (
+                    idx + position_offset,
+                    idx + texcoord_offset,
+                    idx + normal_offset,
+                );
+                match (layout.texcoords, layout.normals) {
+                    (true, true) => write!(obj, " {}/{}/{}", p, t, n)?,
+                    (true, false) => write!(obj, " {}/{}", p, t)?,
+                    (false, true) => write!(obj, " {}//{}", p, n)?,
+                    (false, false) => write!(obj, " {}", p)?,
+                }
+            }
+            writeln!(obj)?;
+        }
+
+        position_offset += mesh.positions.len() / 3;
+        texcoord_offset += mesh.texcoords.len() / 2;
+        normal_offset += mesh.normals.len() / 3;
+    }
+
+    Ok(())
+}
+
+fn write_mtl<W: Write>(
+    mtl: &mut W,
+    materials: &MaterialLibrary,
+    base_dir: Option<&Path>,
+) -> io::Result<()> {
+    for (idx, &(material, ref name)) in materials.materials.iter().enumerate() {
+        if idx > 0 {
+            writeln!(mtl)?;
+        }
+
+        writeln!(mtl, "newmtl {}", name)?;
+
+        // Sort by key so output is deterministic regardless of hash map order
+        let mut maps: Vec<_> = material.maps().iter().collect();
+        maps.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (key, path) in maps {
+            let path = base_dir
+                .and_then(|dir| relative_path(dir, path))
+                .unwrap_or_else(|| path.clone());
+
+            writeln!(mtl, "{} {}", key, path.display())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Expresses `path` relative to the directory `base`.
+///
+/// Returns `None` if one path is absolute and the other relative, or if they
+/// are on different prefixes, e.g. drive letters on windows.
+fn relative_path(base: &Path, path: &Path) -> Option<PathBuf> {
+    if base.is_absolute() != path.is_absolute() {
+        return None;
+    }
+
+    let base: Vec<Component> = base.components().collect();
+    let path: Vec<Component> = path.components().collect();
+
+    let common = base
+        .iter()
+        .zip(path.iter())
+        .take_while(|&(a, b)| a == b)
+        .count();
+
+    let base_rest = &base[common..];
+    if base_rest
+        .iter()
+        .any(|c| !matches!(*c, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+
+    let mut relative = PathBuf::new();
+    for c in base_rest {
+        if let Component::Normal(_) = *c {
+            relative.push("..");
+        }
+    }
+    for c in &path[common..] {
+        relative.push(c.as_os_str());
+    }
+
+    Some(relative)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::MaterialBuilder;
+    use mesh::DeinterleavedIndexedMeshBuf;
+    use std::str;
+    use transform::Transform;
+
+    #[test]
+    fn test_write_obj() {
+        let mat = Rc::new(MaterialBuilder::new().name("Stone").build());
+        let entities = vec![
+            Entity::new(make_mesh(), "First", Rc::clone(&mat)),
+            Entity::new(make_mesh(), "Second", Rc::clone(&mat))
+                .with_transform(Transform::from_translation(Vec3::new(0.0, 0.0, 2.0))),
+        ];
+        let materials = MaterialLibrary::new(&entities);
+
+        let mut obj = Vec::new();
+        write_obj(&mut obj, &entities, &materials, Path::new("scene.mtl")).unwrap();
+        let obj = str::from_utf8(&obj).unwrap();
+        let lines: Vec<&str> = obj.lines().collect();
+
+        assert_eq!(lines[0], "mtllib scene.mtl");
+        assert_eq!(lines[1], "o First");
+        assert_eq!(lines[2], "v 0 0 0");
+        assert_eq!(lines[5], "vn 0 0 1");
+        assert_eq!(lines[8], "vt 0 0");
+        assert_eq!(lines[11], "usemtl Stone");
+        assert_eq!(lines[12], "f 1/1/1 2/2/2 3/3/3");
+        assert_eq!(lines[13], "o Second");
+        assert_eq!(lines[14], "v 0 0 2");
+        assert_eq!(lines[24], "f 4/4/4 5/5/5 6/6/6");
+        assert_eq!(lines.len(), 25);
+    }
+
+    #[test]
+    fn test_write_obj_without_texcoords() {
+        let mut mesh = make_mesh();
+        mesh.texcoords.clear();
+        let entities = vec![Entity::new(
+            mesh,
+            "Plain",
+            Rc::new(MaterialBuilder::new().build()),
+        )];
+        let materials = MaterialLibrary::new(&entities);
+
+        let mut obj = Vec::new();
+        write_obj(&mut obj, &entities, &materials, Path::new("scene.mtl")).unwrap();
+        let obj = str::from_utf8(&obj).unwrap();
+
+        assert!(!obj.contains("vt "));
+        assert_eq!(obj.lines().last(), Some("f 1//1 2//2 3//3"));
+    }
+
+    #[test]
+    fn test_write_obj_mixed_layouts() {
+        let mut plain = make_mesh();
+        plain.texcoords.clear();
+        let mut unlit = make_mesh();
+        unlit.normals.clear();
+        let mat = Rc::new(MaterialBuilder::new().build());
+        let entities = vec![
+            Entity::new(plain, "Plain", Rc::clone(&mat)),
+            Entity::new(make_mesh(), "Full", Rc::clone(&mat)),
+            Entity::new(unlit, "Unlit", Rc::clone(&mat)),
+        ];
+        let materials = MaterialLibrary::new(&entities);
+
+        let mut obj = Vec::new();
+        write_obj(&mut obj, &entities, &materials, Path::new("scene.mtl")).unwrap();
+        let obj = str::from_utf8(&obj).unwrap();
+        let faces: Vec<&str> = obj.lines().filter(|l| l.starts_with("f ")).collect();
+
+        assert_eq!(faces[0], "f 1//1 2//2 3//3");
+        assert_eq!(faces[1], "f 4/1/4 5/2/5 6/3/6");
+        assert_eq!(faces[2], "f 7/4 8/5 9/6");
+        assert_eq!(obj.lines().filter(|l| l.starts_with("vt ")).count(), 6);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("vn ")).count(), 6);
+    }
+
+    #[test]
+    fn test_write_obj_with_colors() {
+        let mut mesh = make_mesh();
+        mesh.colors = vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.5, 0.0, 0.0, 1.0, 1.0];
+        let entities = vec![Entity::new(
+            mesh,
+            "Colorful",
+            Rc::new(MaterialBuilder::new().build()),
+        )];
+        let materials = MaterialLibrary::new(&entities);
+
+        let mut obj = Vec::new();
+        write_obj(&mut obj, &entities, &materials, Path::new("scene.mtl")).unwrap();
+        let obj = str::from_utf8(&obj).unwrap();
+        let lines: Vec<&str> = obj.lines().collect();
+
+        assert_eq!(lines[2], "v 0 0 0 1 0 0");
+        assert_eq!(lines[3], "v 1 0 0 0 1 0");
+    }
+
+    #[test]
+    fn test_deduplicate_materials() {
+        let stone = Rc::new(
+            MaterialBuilder::new()
+                .name("Stone")
+                .diffuse_color_map("/tmp/tex/stone.png")
+                .build(),
+        );
+        let equal_stone = Rc::new(Material::clone(&stone));
+        let other_stone = Rc::new(
+            MaterialBuilder::from(&*stone)
+                .bump_map("/tmp/tex/stone_bump.png")
+                .build(),
+        );
+
+        let entities = vec![
+            Entity::new(make_mesh(), "A", Rc::clone(&stone)),
+            Entity::new(make_mesh(), "B", Rc::clone(&stone)),
+            Entity::new(make_mesh(), "C", equal_stone),
+            Entity::new(make_mesh(), "D", other_stone),
+        ];
+        let materials = MaterialLibrary::new(&entities);
+
+        assert_eq!(materials.materials.len(), 2);
+        assert_eq!(materials.name_of(&entities[2].material), "Stone");
+        assert_eq!(materials.name_of(&entities[3].material), "Stone_1");
+
+        let mut mtl = Vec::new();
+        write_mtl(&mut mtl, &materials, Some(Path::new("/tmp/out"))).unwrap();
+        let mtl = str::from_utf8(&mtl).unwrap();
+
+        assert_eq!(
+            mtl,
+            "newmtl Stone\n\
+             map_Kd ../tex/stone.png\n\
+             \n\
+             newmtl Stone_1\n\
+             bump ../tex/stone_bump.png\n\
+             map_Kd ../tex/stone.png\n"
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/tmp/out"), Path::new("/tmp/out/tex/a.png")),
+            Some(PathBuf::from("tex/a.png"))
+        );
+        assert_eq!(
+            relative_path(Path::new("/tmp/out/obj"), Path::new("/tmp/tex/a.png")),
+            Some(PathBuf::from("../../tex/a.png"))
+        );
+        assert_eq!(
+            relative_path(Path::new("/tmp/out"), Path::new("tex/a.png")),
+            None
+        );
+    }
+
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,760 @@
+use mesh::{CustomAttribute, DeinterleavedIndexedMeshBuf};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::{self, SplitWhitespace};
+
+/// Errors that can occur while loading meshes from PLY files.
+#[derive(Debug)]
+pub enum PlyLoadError {
+    /// The PLY file could not be read.
+    Io(io::Error),
+    /// The header was malformed or declared something unsupported.
+    Header(String),
+    /// The data after the header did not match the header.
+    Body(String),
+    /// A face referenced a vertex that does not exist.
+    IndexOutOfRange { index: u32, vertex_count: usize },
+}
+
+impl fmt::Display for PlyLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlyLoadError::Io(ref cause) => write!(f, "Could not read PLY file: {}", cause),
+            PlyLoadError::Header(ref msg) => write!(f, "Malformed PLY header: {}", msg),
+            PlyLoadError::Body(ref msg) => write!(f, "Malformed PLY data: {}", msg),
+            PlyLoadError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "Face references vertex {} but only {} vertices were declared",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+impl Error for PlyLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PlyLoadError::Io(ref cause) => Some(cause),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlyLoadError {
+    fn from(err: io::Error) -> Self {
+        PlyLoadError::Io(err)
+    }
+}
+
+/// Loads a mesh from a PLY file at the given path, in ASCII or binary format.
+///
+/// Vertex properties are loaded into the attributes of the mesh as follows:
+///
+/// * `x`, `y` and `z` into positions, which are required,
+/// * `nx`, `ny` and `nz` into normals,
+/// * `s` and `t`, `u` and `v` or `texture_u` and `texture_v` into texture coordinates,
+///   and `s1` and `t1`, `s2` and `t2`, and so on into additional texture coordinate sets,
+///   where sets with missing numbers in between become empty sets,
+/// * `tx`, `ty`, `tz` and `tw` into tangents,
+/// * `red`, `green`, `blue` and optionally `alpha` into colors, where integer
+///   components are scaled from their full range to the range from zero to one,
+/// * all other properties into custom attributes, where properties of unsigned
+///   integer type become `u32` attributes and all others `f32` attributes.
+///   Floating point properties with the same name and the suffixes `_0`, `_1`,
+///   and so on are combined into one attribute with up to four components.
+///
+/// This is the inverse of [`save_ply`](fn.save_ply.html). Faces are loaded from
+/// the `vertex_indices` or `vertex_index` list property of the `face` element,
+/// and polygons with more than three vertices are split into triangle fans.
+/// Other elements are ignored.
+///
+/// ```no_run
+/// use aitios_scene::load_ply;
+///
+/// let scan = load_ply("/tmp/scan.ply").unwrap();
+/// println!("First vertex has color {:?}", scan.color_at(0));
+/// ```
+pub fn load_ply<P: AsRef<Path>>(path: P) -> Result<DeinterleavedIndexedMeshBuf, PlyLoadError> {
+    let mut ply = BufReader::new(File::open(path)?);
+    read_ply(&mut ply)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, PlyLoadError> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::Int8),
+            "uchar" | "uint8" => Ok(ScalarType::UInt8),
+            "short" | "int16" => Ok(ScalarType::Int16),
+            "ushort" | "uint16" => Ok(ScalarType::UInt16),
+            "int" | "int32" => Ok(ScalarType::Int32),
+            "uint" | "uint32" => Ok(ScalarType::UInt32),
+            "float" | "float32" => Ok(ScalarType::Float32),
+            "double" | "float64" => Ok(ScalarType::Float64),
+            _ => Err(PlyLoadError::Header(format!("Unknown type {}", name))),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// Gets the largest value of unsigned integer types, or `None` for other types.
+    fn unsigned_max(self) -> Option<f64> {
+        match self {
+            ScalarType::UInt8 => Some(f64::from(u8::MAX)),
+            ScalarType::UInt16 => Some(f64::from(u16::MAX)),
+            ScalarType::UInt32 => Some(f64::from(u32::MAX)),
+            _ => None,
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        ty: ScalarType,
+    },
+    List {
+        name: String,
+        count_ty: ScalarType,
+        item_ty: ScalarType,
+    },
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Values of the body of a PLY file, in the order they are declared in the header.
+enum Body<'a> {
+    Ascii(SplitWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, PlyLoadError> {
+        match *self {
+            Body::Ascii(ref mut tokens) => {
+                let token = tokens.next().ok_or_else(unexpected_end)?;
+                token
+                    .parse()
+                    .map_err(|_| PlyLoadError::Body(format!("Invalid value {}", token)))
+            }
+            Body::Binary {
+                ref mut data,
+                big_endian,
+            } => {
+                let size = ty.size();
+                let remaining: &'a [u8] = *data;
+                if remaining.len() < size {
+                    return Err(unexpected_end());
+                }
+
+                let (value, rest) = remaining.split_at(size);
+                *data = rest;
+
+                // Convert to little endian, padded to the largest size
+                let mut b = [0_u8; 8];
+                b[..size].copy_from_slice(value);
+                if big_endian {
+                    b[..size].reverse();
+                }
+
+                Ok(match ty {
+                    ScalarType::Int8 => f64::from(b[0] as i8),
+                    ScalarType::UInt8 => f64::from(b[0]),
+                    ScalarType::Int16 => f64::from(i16::from_le_bytes([b[0], b[1]])),
+                    ScalarType::UInt16 => f64::from(u16::from_le_bytes([b[0], b[1]])),
+                    ScalarType::Int32 => f64::from(i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                    ScalarType::UInt32 => f64::from(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                    ScalarType::Float32 => f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                    ScalarType::Float64 => f64::from_le_bytes(b),
+                })
+            }
+        }
+    }
+
+    /// Reads a value that must be a non-negative integer, such as a list
+    /// count or a vertex index.
+    fn read_u32(&mut self, ty: ScalarType) -> Result<u32, PlyLoadError> {
+        let value = self.read(ty)?;
+        if value >= 0.0 && value <= f64::from(u32::MAX) && value.fract() == 0.0 {
+            Ok(value as u32)
+        } else {
+            Err(PlyLoadError::Body(format!(
+                "Expected non-negative integer, got {}",
+                value
+            )))
+        }
+    }
+}
+
+fn unexpected_end() -> PlyLoadError {
+    PlyLoadError::Body(String::from("Unexpected end of data"))
+}
+
+fn read_ply<R: BufRead>(ply: &mut R) -> Result<DeinterleavedIndexedMeshBuf, PlyLoadError> {
+    let (format, elements) = read_header(ply)?;
+
+    let mut data = Vec::new();
+    ply.read_to_end(&mut data)?;
+    let mut body = match format {
+        Format::Ascii => {
+            let text = str::from_utf8(&data)
+                .map_err(|_| PlyLoadError::Body(String::from("ASCII data is not valid UTF-8")))?;
+            Body::Ascii(text.split_whitespace())
+        }
+        Format::BinaryLittleEndian => Body::Binary {
+            data: &data,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Body::Binary {
+            data: &data,
+            big_endian: true,
+        },
+    };
+
+    // One column of values for each scalar vertex property
+    let mut vertex_columns: Vec<Vec<f64>> = Vec::new();
+    let mut vertex_properties: &[Property] = &[];
+    let mut vertex_count = 0;
+    let mut indices = Vec::new();
+
+    // Counts in the header are not trusted with allocations, so that corrupt
+    // files fail with an error when the data runs out
+    for element in &elements {
+        if element.name == "vertex" {
+            vertex_properties = &element.properties;
+            vertex_count = element.count;
+            vertex_columns = element.properties.iter().map(|_| Vec::new()).collect();
+        }
+
+        for _ in 0..element.count {
+            for (property_idx, property) in element.properties.iter().enumerate() {
+                match *property {
+                    Property::Scalar { ty, .. } => {
+                        let value = body.read(ty)?;
+                        if element.name == "vertex" {
+                            vertex_columns[property_idx].push(value);
+                        }
+                    }
+                    Property::List {
+                        ref name,
+                        count_ty,
+                        item_ty,
+                    } => {
+                        let count = body.read_u32(count_ty)?;
+                        let is_face = element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index");
+                        let mut polygon = Vec::new();
+                        for _ in 0..count {
+                            polygon.push(body.read_u32(item_ty)?);
+                        }
+
+                        if is_face {
+                            // Split polygons into triangle fans
+                            for i in 2..polygon.len() {
+                                indices.extend(&[polygon[0], polygon[i - 1], polygon[i]]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+        return Err(PlyLoadError::IndexOutOfRange {
+            index,
+            vertex_count,
+        });
+    }
+
+    let mut mesh = mesh_from_vertex_columns(vertex_properties, &vertex_columns, vertex_count)?;
+    mesh.indices = indices;
+    Ok(mesh)
+}
+
+fn read_header<R: BufRead>(ply: &mut R) -> Result<(Format, Vec<Element>), PlyLoadError> {
+    let mut line = String::new();
+    ply.read_line(&mut line)?;
+    if line.trim() != "ply" {
+        return Err(PlyLoadError::Header(String::from("Missing ply magic")));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    loop {
+        line.clear();
+        if ply.read_line(&mut line)? == 0 {
+            return Err(PlyLoadError::Header(String::from("Missing end_header")));
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let malformed = || PlyLoadError::Header(format!("Malformed line {}", line.trim()));
+
+        match tokens.first().cloned() {
+            Some("format") => {
+                format = Some(match tokens.get(1).cloned() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::BinaryLittleEndian,
+                    Some("binary_big_endian") => Format::BinaryBigEndian,
+                    _ => return Err(malformed()),
+                });
+            }
+            Some("element") => {
+                if tokens.len() != 3 {
+                    return Err(malformed());
+                }
+                elements.push(Element {
+                    name: tokens[1].to_string(),
+                    count: tokens[2].parse().map_err(|_| malformed())?,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(malformed)?;
+                let property = match tokens.len() {
+                    3 => Property::Scalar {
+                        name: tokens[2].to_string(),
+                        ty: ScalarType::parse(tokens[1])?,
+                    },
+                    5 if tokens[1] == "list" => Property::List {
+                        name: tokens[4].to_string(),
+                        count_ty: ScalarType::parse(tokens[2])?,
+                        item_ty: ScalarType::parse(tokens[3])?,
+                    },
+                    _ => return Err(malformed()),
+                };
+                element.properties.push(property);
+            }
+            Some("end_header") => break,
+            Some("comment") | Some("obj_info") | None => (),
+            Some(_) => return Err(malformed()),
+        }
+    }
+
+    let format = format.ok_or_else(|| PlyLoadError::Header(String::from("Missing format")))?;
+    Ok((format, elements))
+}
+
+/// Converts the columns of scalar vertex properties into the attributes of a mesh
+/// without indices.
+fn mesh_from_vertex_columns(
+    properties: &[Property],
+    columns: &[Vec<f64>],
+    vertex_count: usize,
+) -> Result<DeinterleavedIndexedMeshBuf, PlyLoadError> {
+    let mut used = vec![false; properties.len()];
+
+    // Interleaves the named properties and marks them as used, or returns
+    // `None` if any of them is missing. If `normalize` is set, unsigned
+    // integer values are scaled to the range from zero to one.
+    let mut take = |names: &[&str], normalize: bool| -> Option<Vec<f32>> {
+        let found = names
+            .iter()
+            .map(|name| {
+                properties.iter().position(|p| match *p {
+                    Property::Scalar { name: ref n, .. } => n == name,
+                    Property::List { .. } => false,
+                })
+            })
+            .collect::<Option<Vec<usize>>>()?;
+
+        let mut values = Vec::with_capacity(vertex_count * names.len());
+        for vertex_idx in 0..vertex_count {
+            for &property_idx in &found {
+                let value = columns[property_idx][vertex_idx];
+                let max = match properties[property_idx] {
+                    Property::Scalar { ty, .. } if normalize => ty.unsigned_max(),
+                    _ => None,
+                };
+                values.push(max.map_or(value, |max| value / max) as f32);
+            }
+        }
+
+        for &property_idx in &found {
+            used[property_idx] = true;
+        }
+
+        Some(values)
+    };
+
+    let positions = take(&["x", "y", "z"], false)
+        .ok_or_else(|| PlyLoadError::Header(String::from("Missing vertex positions")))?;
+    let normals = take(&["nx", "ny", "nz"], false).unwrap_or_default();
+    let texcoords = take(&["s", "t"], false)
+        .or_else(|| take(&["u", "v"], false))
+        .or_else(|| take(&["texture_u", "texture_v"], false))
+        .unwrap_or_default();
+    // Empty sets are not written, so later sets may follow a gap. Numbers
+    // beyond the property count cannot come from a mesh with that many sets
+    // and are left to custom attributes.
+    let set_count = properties
+        .iter()
+        .filter_map(|p| match *p {
+            Property::Scalar { ref name, .. } if name.starts_with('s') => name[1..].parse().ok(),
+            _ => None,
+        })
+        .filter(|&set_number: &usize| set_number <= properties.len())
+        .max()
+        .unwrap_or(0);
+    let mut extra_texcoords = Vec::with_capacity(set_count);
+    for set_number in 1..=set_count {
+        let (s, t) = (format!("s{}", set_number), format!("t{}", set_number));
+        extra_texcoords.push(take(&[s.as_str(), t.as_str()], false).unwrap_or_default());
+    }
+    // Sets without values at the end are not sets at all
+    while extra_texcoords.last().map_or(false, |set| set.is_empty()) {
+        extra_texcoords.pop();
+    }
+    let tangents = take(&["tx", "ty", "tz", "tw"], false).unwrap_or_default();
+    let colors = take(&["red", "green", "blue", "alpha"], true)
+        .or_else(|| {
+            take(&["red", "green", "blue"], true).map(|rgb| {
+                rgb.chunks(3)
+                    .flat_map(|c| vec![c[0], c[1], c[2], 1.0])
+                    .collect()
+            })
+        })
+        .unwrap_or_default();
+
+    let mut mesh = DeinterleavedIndexedMeshBuf {
+        positions,
+        normals,
+        texcoords,
+        extra_texcoords,
+        tangents,
+        colors,
+        ..Default::default()
+    };
+
+    let mut property_idx = 0;
+    while property_idx < properties.len() {
+        let (name, ty) = match properties[property_idx] {
+            Property::Scalar { ref name, ty } if !used[property_idx] => (name, ty),
+            _ => {
+                property_idx += 1;
+                continue;
+            }
+        };
+
+        if ty.unsigned_max().is_some() {
+            let values = columns[property_idx].iter().map(|&v| v as u32).collect();
+            mesh.custom_attributes
+                .insert(name.clone(), CustomAttribute::U32(values));
+            property_idx += 1;
+            continue;
+        }
+
+        // Combine float properties like rust_0 and rust_1 into one attribute
+        let components = if name.ends_with("_0") {
+            let base = &name[..name.len() - 2];
+            (1..4)
+                .take_while(|&c| match properties.get(property_idx + c) {
+                    Some(&Property::Scalar { ref name, ty }) => {
+                        ty.unsigned_max().is_none() && *name == format!("{}_{}", base, c)
+                    }
+                    _ => false,
+                })
+                .count()
+                + 1
+        } else {
+            1
+        };
+
+        let (name, columns) = if components > 1 {
+            (
+                name[..name.len() - 2].to_string(),
+                &columns[property_idx..property_idx + components],
+            )
+        } else {
+            (name.clone(), &columns[property_idx..property_idx + 1])
+        };
+
+        let mut values = Vec::with_capacity(vertex_count * components);
+        for vertex_idx in 0..vertex_count {
+            for column in columns {
+                values.push(column[vertex_idx] as f32);
+            }
+        }
+
+        mesh.custom_attributes
+            .insert(name, CustomAttribute::F32 { components, values });
+        property_idx += components;
+    }
+
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ply::save::write_ply;
+
+    #[test]
+    fn test_read_ascii() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   comment scanned\n\
+                   element vertex 4\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   property uchar red\n\
+                   property uchar green\n\
+                   property uchar blue\n\
+                   element face 1\n\
+                   property list uchar int vertex_indices\n\
+                   end_header\n\
+                   0 0 0 255 0 0\n\
+                   1 0 0 0 255 0\n\
+                   1 1 0 0 0 255\n\
+                   0 1 0 255 255 255\n\
+                   4 0 1 2 3\n";
+
+        let mesh = read_ply(&mut ply.as_bytes()).unwrap();
+
+        assert_eq!(mesh.positions.len(), 12);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.color_at(1), Some([0.0, 1.0, 0.0, 1.0]));
+        assert!(mesh.normals.is_empty());
+        assert!(mesh.custom_attributes.is_empty());
+    }
+
+    #[test]
+    fn test_read_binary_big_endian() {
+        let mut ply = b"ply\n\
+                        format binary_big_endian 1.0\n\
+                        element vertex 3\n\
+                        property float x\n\
+                        property float y\n\
+                        property float z\n\
+                        element face 1\n\
+                        property list uchar ushort vertex_index\n\
+                        end_header\n"
+            .to_vec();
+        for &value in &[0.0_f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            ply.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        ply.push(3);
+        for &index in &[2_u16, 1, 0] {
+            ply.extend_from_slice(&index.to_be_bytes());
+        }
+
+        let mesh = read_ply(&mut &ply[..]).unwrap();
+
+        assert_eq!(
+            mesh.positions,
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        );
+        assert_eq!(mesh.indices, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            extra_texcoords: vec![vec![0.5, 0.5, 1.0, 0.5, 0.5, 1.0]],
+            colors: vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        mesh.custom_attributes.insert(
+            String::from("rust"),
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5],
+            },
+        );
+        mesh.custom_attributes
+            .insert(String::from("region"), CustomAttribute::U32(vec![7, 8, 9]));
+
+        let mut ply = Vec::new();
+        write_ply(&mut ply, &mesh).unwrap();
+        let loaded = read_ply(&mut &ply[..]).unwrap();
+
+        assert_eq!(loaded.positions, mesh.positions);
+        assert_eq!(loaded.texcoords, mesh.texcoords);
+        assert_eq!(loaded.extra_texcoords, mesh.extra_texcoords);
+        assert_eq!(loaded.colors, mesh.colors);
+        assert_eq!(loaded.custom_attributes, mesh.custom_attributes);
+        assert_eq!(loaded.indices, mesh.indices);
+    }
+
+    #[test]
+    fn test_roundtrip_component_suffixes() {
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let scalar = |value: f32| CustomAttribute::F32 {
+            components: 1,
+            values: vec![value; 3],
+        };
+
+        // Read back, these would merge into one attribute w with two components
+        let mut separate = DeinterleavedIndexedMeshBuf {
+            positions: positions.clone(),
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        separate
+            .custom_attributes
+            .insert(String::from("w_0"), scalar(1.0));
+        separate
+            .custom_attributes
+            .insert(String::from("w_1"), scalar(2.0));
+        let err = write_ply(&mut Vec::new(), &separate).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // Read back, a_2 would become the third component of a
+        let mut extended = DeinterleavedIndexedMeshBuf {
+            positions: positions.clone(),
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        extended.custom_attributes.insert(
+            String::from("a"),
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![0.5; 6],
+            },
+        );
+        extended
+            .custom_attributes
+            .insert(String::from("a_2"), scalar(3.0));
+        let err = write_ply(&mut Vec::new(), &extended).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // Suffixes are fine where the loader cannot mistake them for components
+        let mut mesh = extended;
+        mesh.custom_attributes.remove("a_2");
+        mesh.custom_attributes.insert(
+            String::from("layer_1"),
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5],
+            },
+        );
+        mesh.custom_attributes.insert(
+            String::from("region_2"),
+            CustomAttribute::U32(vec![7, 8, 9]),
+        );
+
+        let mut ply = Vec::new();
+        write_ply(&mut ply, &mesh).unwrap();
+        let loaded = read_ply(&mut &ply[..]).unwrap();
+
+        assert_eq!(loaded.custom_attributes, mesh.custom_attributes);
+    }
+
+    #[test]
+    fn test_roundtrip_texcoord_set_gap() {
+        let mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            extra_texcoords: vec![vec![], vec![0.5, 0.5, 1.0, 0.5, 0.5, 1.0]],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+
+        let mut ply = Vec::new();
+        write_ply(&mut ply, &mesh).unwrap();
+        let loaded = read_ply(&mut &ply[..]).unwrap();
+
+        assert_eq!(loaded.extra_texcoords, mesh.extra_texcoords);
+        assert!(loaded.custom_attributes.is_empty());
+    }
+
+    #[test]
+    fn test_index_out_of_range() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   element vertex 1\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   element face 1\n\
+                   property list uchar int vertex_indices\n\
+                   end_header\n\
+                   0 0 0\n\
+                   3 0 0 1\n";
+
+        match read_ply(&mut ply.as_bytes()) {
+            Err(PlyLoadError::IndexOutOfRange { index: 1, .. }) => (),
+            _ => panic!("Expected index out of range error"),
+        }
+    }
+
+    #[test]
+    fn test_negative_index() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   element vertex 3\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   element face 1\n\
+                   property list uchar int vertex_indices\n\
+                   end_header\n\
+                   0 0 0\n\
+                   1 0 0\n\
+                   0 1 0\n\
+                   3 0 1 -2\n";
+
+        match read_ply(&mut ply.as_bytes()) {
+            Err(PlyLoadError::Body(_)) => (),
+            _ => panic!("Expected error for negative index"),
+        }
+    }
+
+    #[test]
+    fn test_truncated_with_huge_counts() {
+        let ply = "ply\n\
+                   format binary_little_endian 1.0\n\
+                   element vertex 4000000000000\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   element face 4000000000000\n\
+                   property list uint int vertex_indices\n\
+                   end_header\n";
+        let mut ply = ply.as_bytes().to_vec();
+        ply.extend_from_slice(&[0; 12]);
+
+        match read_ply(&mut &ply[..]) {
+            Err(PlyLoadError::Body(_)) => (),
+            _ => panic!("Expected error for truncated data"),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod load;
+mod save;
+
+pub use self::load::{load_ply, PlyLoadError};
+pub use self::save::save_ply;
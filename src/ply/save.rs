@@ -0,0 +1,333 @@
+use mesh::{CustomAttribute, DeinterleavedIndexedMeshBuf};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writes a mesh to an ASCII PLY file at the given path.
+///
+/// Unlike OBJ, PLY can store arbitrary per-vertex properties, so all attributes
+/// of the mesh are written, including its custom attributes:
+///
+/// * Positions as `x`, `y` and `z`,
+/// * normals, if present, as `nx`, `ny` and `nz`,
+/// * texture coordinates, if present, as `s` and `t`, and additional texture
+///   coordinate sets as `s1` and `t1`, `s2` and `t2`, and so on,
+/// * tangents, if present, as `tx`, `ty`, `tz` and `tw`,
+/// * colors, if present, as `red`, `green`, `blue` and `alpha`, with each
+///   component clamped to the range from zero to one and scaled to a `uchar`,
+/// * custom attributes with one component as a property with the name of the
+///   attribute, and with more components as one property per component with
+///   the component index as suffix, e.g. `rust_0` and `rust_1`.
+///
+/// Custom attributes that could not be read back unambiguously are rejected
+/// with an error of kind `InvalidInput` before anything is written:
+///
+/// * Attributes with property names that clash with the properties of the
+///   other attributes, such as `x` or `s1`, or with each other,
+/// * attributes with empty names or names containing whitespace,
+/// * floating point attributes with one component and a name ending in a
+///   component suffix like `_2`, which would be read as a component of
+///   another attribute.
+///
+/// Incomplete triangles at the end of the indices are not written.
+///
+/// ```no_run
+/// use aitios_scene::{load_obj, save_ply};
+///
+/// let entities = load_obj("/tmp/buildings.obj").unwrap();
+/// save_ply(&entities[0].mesh, "/tmp/building.ply").unwrap();
+/// ```
+pub fn save_ply<P: AsRef<Path>>(mesh: &DeinterleavedIndexedMeshBuf, path: P) -> io::Result<()> {
+    let mut ply = BufWriter::new(File::create(path)?);
+    write_ply(&mut ply, mesh)?;
+    ply.flush()
+}
+
+pub(crate) fn write_ply<W: Write>(
+    ply: &mut W,
+    mesh: &DeinterleavedIndexedMeshBuf,
+) -> io::Result<()> {
+    let vertex_count = mesh.positions.len() / 3;
+    let custom_attributes = custom_properties(mesh)?;
+    let triangles: Vec<&[u32]> = mesh
+        .indices
+        .chunks(3)
+        .filter(|tri| tri.len() == 3)
+        .collect();
+
+    writeln!(ply, "ply")?;
+    writeln!(ply, "format ascii 1.0")?;
+    writeln!(ply, "comment written by aitios-scene")?;
+    writeln!(ply, "element vertex {}", vertex_count)?;
+
+    let mut attributes: Vec<(&[f32], usize)> = vec![(&mesh.positions, 3)];
+    write_properties(ply, "float", &["x", "y", "z"])?;
+
+    if !mesh.normals.is_empty() {
+        attributes.push((&mesh.normals, 3));
+        write_properties(ply, "float", &["nx", "ny", "nz"])?;
+    }
+
+    if !mesh.texcoords.is_empty() {
+        attributes.push((&mesh.texcoords, 2));
+        write_properties(ply, "float", &["s", "t"])?;
+    }
+
+    for (set_idx, set) in mesh.extra_texcoords.iter().enumerate() {
+        if !set.is_empty() {
+            attributes.push((set, 2));
+            let (s, t) = (format!("s{}", set_idx + 1), format!("t{}", set_idx + 1));
+            write_properties(ply, "float", &[s.as_str(), t.as_str()])?;
+        }
+    }
+
+    if !mesh.tangents.is_empty() {
+        attributes.push((&mesh.tangents, 4));
+        write_properties(ply, "float", &["tx", "ty", "tz", "tw"])?;
+    }
+
+    if !mesh.colors.is_empty() {
+        write_properties(ply, "uchar", &["red", "green", "blue", "alpha"])?;
+    }
+
+    for &(attribute, ref names) in &custom_attributes {
+        let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+        let ty = match *attribute {
+            CustomAttribute::F32 { .. } => "float",
+            CustomAttribute::U32(_) => "uint",
+        };
+        write_properties(ply, ty, &names)?;
+    }
+
+    writeln!(ply, "element face {}", triangles.len())?;
+    writeln!(ply, "property list uchar uint vertex_indices")?;
+    writeln!(ply, "end_header")?;
+
+    for vertex_idx in 0..vertex_count {
+        let mut first = true;
+        let mut separator = |ply: &mut W| {
+            if first {
+                first = false;
+                Ok(())
+            } else {
+                write!(ply, " ")
+            }
+        };
+
+        for &(values, stride) in &attributes {
+            for value in &values[vertex_idx * stride..(vertex_idx + 1) * stride] {
+                separator(ply)?;
+                write!(ply, "{}", value)?;
+            }
+        }
+
+        if !mesh.colors.is_empty() {
+            for &c in &mesh.colors[vertex_idx * 4..(vertex_idx + 1) * 4] {
+                separator(ply)?;
+                write!(ply, "{}", (c.max(0.0).min(1.0) * 255.0).round() as u8)?;
+            }
+        }
+
+        for &(attribute, _) in &custom_attributes {
+            let components = attribute.components();
+            let range = vertex_idx * components..(vertex_idx + 1) * components;
+            match *attribute {
+                CustomAttribute::F32 { ref values, .. } => {
+                    for value in &values[range] {
+                        separator(ply)?;
+                        write!(ply, "{}", value)?;
+                    }
+                }
+                CustomAttribute::U32(ref values) => {
+                    for value in &values[range] {
+                        separator(ply)?;
+                        write!(ply, "{}", value)?;
+                    }
+                }
+            }
+        }
+
+        writeln!(ply)?;
+    }
+
+    for tri in triangles {
+        writeln!(ply, "3 {} {} {}", tri[0], tri[1], tri[2])?;
+    }
+
+    Ok(())
+}
+
+/// Gets the non-empty custom attributes of the mesh with the names of their
+/// properties, or an error if a name is reserved, occurs twice, is no valid
+/// property name or could be mistaken for a component of another attribute.
+fn custom_properties(
+    mesh: &DeinterleavedIndexedMeshBuf,
+) -> io::Result<Vec<(&CustomAttribute, Vec<String>)>> {
+    let mut taken = HashSet::new();
+    let mut properties = Vec::new();
+
+    for (name, attribute) in &mesh.custom_attributes {
+        if attribute.is_empty() {
+            continue;
+        }
+
+        let components = attribute.components();
+        let mistaken_for_component = match *attribute {
+            CustomAttribute::F32 { .. } => components == 1 && has_component_suffix(name),
+            CustomAttribute::U32(_) => false,
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) || mistaken_for_component {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Custom attribute name {:?} cannot be stored in PLY", name),
+            ));
+        }
+
+        let names: Vec<String> = if components == 1 {
+            vec![name.clone()]
+        } else {
+            (0..components).map(|c| format!("{}_{}", name, c)).collect()
+        };
+
+        for name in &names {
+            if is_reserved_property(name) || !taken.insert(name.clone()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Custom attribute property {} clashes with another property",
+                        name
+                    ),
+                ));
+            }
+        }
+
+        properties.push((attribute, names));
+    }
+
+    Ok(properties)
+}
+
+/// Checks whether the loader reads a vertex property with the given name
+/// into something other than a custom attribute.
+fn is_reserved_property(name: &str) -> bool {
+    const RESERVED: &[&str] = &[
+        "x",
+        "y",
+        "z",
+        "nx",
+        "ny",
+        "nz",
+        "s",
+        "t",
+        "u",
+        "v",
+        "texture_u",
+        "texture_v",
+        "tx",
+        "ty",
+        "tz",
+        "tw",
+        "red",
+        "green",
+        "blue",
+        "alpha",
+    ];
+    let texcoord_set = (name.starts_with('s') || name.starts_with('t'))
+        && name.len() > 1
+        && name[1..].bytes().all(|b| b.is_ascii_digit());
+
+    RESERVED.contains(&name) || texcoord_set
+}
+
+/// Checks whether the name ends in an underscore followed by digits, which the
+/// loader treats as the component index of a floating point attribute.
+fn has_component_suffix(name: &str) -> bool {
+    match name.rfind('_') {
+        Some(underscore) => {
+            let suffix = &name[underscore + 1..];
+            !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+fn write_properties<W: Write>(ply: &mut W, ty: &str, names: &[&str]) -> io::Result<()> {
+    for name in names {
+        writeln!(ply, "property {} {}", ty, name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str;
+
+    #[test]
+    fn test_write_ply() {
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2, 0],
+            ..Default::default()
+        };
+        mesh.custom_attributes.insert(
+            String::from("rust_density"),
+            CustomAttribute::F32 {
+                components: 2,
+                values: vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5],
+            },
+        );
+        mesh.custom_attributes
+            .insert(String::from("region"), CustomAttribute::U32(vec![7, 8, 9]));
+
+        let mut ply = Vec::new();
+        write_ply(&mut ply, &mesh).unwrap();
+        let ply = str::from_utf8(&ply).unwrap();
+        let lines: Vec<&str> = ply.lines().collect();
+
+        assert_eq!(lines[3], "element vertex 3");
+        assert_eq!(lines[4], "property float x");
+        assert_eq!(lines[7], "property float nx");
+        assert_eq!(lines[10], "property uint region");
+        assert_eq!(lines[11], "property float rust_density_0");
+        assert_eq!(lines[12], "property float rust_density_1");
+        assert_eq!(lines[13], "element face 1");
+        assert_eq!(lines[15], "end_header");
+        assert_eq!(lines[17], "1 0 0 0 0 1 8 1 1.5");
+        assert_eq!(lines[19], "3 0 1 2");
+        assert_eq!(lines.len(), 20);
+    }
+
+    #[test]
+    fn test_reject_clashing_names() {
+        let mut mesh = DeinterleavedIndexedMeshBuf {
+            positions: vec![0.0, 0.0, 0.0],
+            ..Default::default()
+        };
+        for name in &["s1", "red", "x"] {
+            let mut clashing = mesh.clone();
+            clashing
+                .custom_attributes
+                .insert(name.to_string(), CustomAttribute::U32(vec![1]));
+            let err = write_ply(&mut Vec::new(), &clashing).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        mesh.custom_attributes
+            .insert(String::from("wet ness"), CustomAttribute::U32(vec![1]));
+        let mut ply = Vec::new();
+        assert!(write_ply(&mut ply, &mesh).is_err());
+        assert!(ply.is_empty());
+    }
+
+    #[test]
+    fn test_component_suffix() {
+        assert!(has_component_suffix("w_0"));
+        assert!(has_component_suffix("layer_12"));
+        assert!(!has_component_suffix("w_"));
+        assert!(!has_component_suffix("w0"));
+        assert!(!has_component_suffix("w_0a"));
+    }
+}
@@ -0,0 +1,411 @@
+use entity::Entity;
+use geom::{Aabb, TupleTriangle, Vec3};
+use material::Material;
+use mesh::{ColoredVertex, DeinterleavedIndexedMeshBuf, Mesh};
+use std::collections::HashSet;
+use std::iter::FromIterator;
+use std::rc::Rc;
+use std::slice;
+use sync::{from_sync_entities, to_sync_entities, SyncEntity};
+use transform::Transform;
+
+/// Owns a set of entities and the library of materials they reference.
+///
+/// Materials are shared between entities where possible: when an entity is
+/// added with a material that is equal to one already in the library, the
+/// entity is changed to reference the material from the library instead.
+///
+/// ```
+/// use aitios_scene::{DeinterleavedIndexedMeshBuf, Entity, MaterialBuilder, Scene};
+/// use std::rc::Rc;
+///
+/// let mut scene = Scene::new();
+/// let stone = scene.add_material(MaterialBuilder::new().name("Stone").build());
+///
+/// scene.add_entity(Entity::new(DeinterleavedIndexedMeshBuf::default(), "Wall", Rc::clone(&stone)));
+/// // An equal material is replaced with the one in the library
+/// let stone_copy = Rc::new(MaterialBuilder::new().name("Stone").build());
+/// scene.add_entity(Entity::new(DeinterleavedIndexedMeshBuf::default(), "Floor", stone_copy));
+///
+/// assert_eq!(scene.material_count(), 1);
+/// assert!(Rc::ptr_eq(&scene.entity("Floor").unwrap().material, &stone));
+/// ```
+#[derive(Clone)]
+pub struct Scene {
+    entities: Vec<Entity>,
+    materials: Vec<Rc<Material>>,
+}
+
+impl Scene {
+    /// Creates an empty scene without entities or materials.
+    pub fn new() -> Self {
+        Scene {
+            entities: Vec::new(),
+            materials: Vec::new(),
+        }
+    }
+
+    /// Adds an entity to the scene and its material to the material library.
+    ///
+    /// If an equal material is already present in the library, the entity
+    /// will reference that material instead of its own.
+    pub fn add_entity(&mut self, mut entity: Entity) {
+        entity.material = self.add_shared_material(entity.material);
+        self.entities.push(entity);
+    }
+
+    /// Removes the first entity with the given name and returns it.
+    ///
+    /// The material of the entity stays in the library, use
+    /// [`remove_unused_materials`](#method.remove_unused_materials) to get rid of it.
+    pub fn remove_entity(&mut self, name: &str) -> Option<Entity> {
+        self.entities
+            .iter()
+            .position(|e| e.name == name)
+            .map(|idx| self.entities.remove(idx))
+    }
+
+    /// Adds a material to the material library and returns a shared reference
+    /// to it. If an equal material is already in the library, the reference to
+    /// that material is returned instead.
+    pub fn add_material(&mut self, material: Material) -> Rc<Material> {
+        self.add_shared_material(Rc::new(material))
+    }
+
+    /// Like [`add_material`](#method.add_material), but for an already shared material.
+    pub fn add_shared_material(&mut self, material: Rc<Material>) -> Rc<Material> {
+        let existing = self
+            .materials
+            .iter()
+            .find(|m| Rc::ptr_eq(m, &material) || ***m == *material);
+
+        match existing {
+            Some(existing) => Rc::clone(existing),
+            None => {
+                self.materials.push(Rc::clone(&material));
+                material
+            }
+        }
+    }
+
+    /// Removes materials from the library that are not referenced by any entity.
+    pub fn remove_unused_materials(&mut self) {
+        let entities = &self.entities;
+        self.materials
+            .retain(|m| entities.iter().any(|e| Rc::ptr_eq(&e.material, m)));
+    }
+
+    /// Adds an instance of the first entity with the given name to the scene,
+    /// sharing mesh and material with the original but with another transform.
+    ///
+    /// Returns `false` and does nothing if no entity has the given name.
+    pub fn add_instance<S>(&mut self, original: &str, name: S, transform: Transform) -> bool
+    where
+        S: Into<String>,
+    {
+        self.add_instance_with_material(original, name, transform, None)
+    }
+
+    /// Like [`add_instance`](#method.add_instance), but if a material is
+    /// given, the instance gets that material instead of the one of the
+    /// original. The material is added to the library like with
+    /// [`add_entity`](#method.add_entity).
+    pub fn add_instance_with_material<S>(
+        &mut self,
+        original: &str,
+        name: S,
+        transform: Transform,
+        material: Option<Rc<Material>>,
+    ) -> bool
+    where
+        S: Into<String>,
+    {
+        let instance = self.entity(original).map(|e| match material {
+            Some(material) => e.instantiate_with_material(name, transform, material),
+            None => e.instantiate(name, transform),
+        });
+
+        match instance {
+            Some(instance) => {
+                self.add_entity(instance);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over all entities that reference the given mesh.
+    pub fn instances_of<'a>(
+        &'a self,
+        mesh: &'a Rc<DeinterleavedIndexedMeshBuf>,
+    ) -> impl Iterator<Item = &'a Entity> + 'a {
+        self.entities
+            .iter()
+            .filter(move |e| Rc::ptr_eq(&e.mesh, mesh))
+    }
+
+    /// Counts the distinct meshes referenced by entities in the scene, which
+    /// can be lower than the amount of entities if meshes are instanced.
+    pub fn mesh_count(&self) -> usize {
+        self.entities
+            .iter()
+            .map(|e| Rc::as_ptr(&e.mesh))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Iterates over the triangles of all entities in world space.
+    ///
+    /// Transforms are applied on the fly, so instanced meshes are not
+    /// duplicated in memory.
+    pub fn triangles(&self) -> impl Iterator<Item = TupleTriangle<ColoredVertex>> + '_ {
+        self.entities.iter().flat_map(|e| e.triangles())
+    }
+
+    /// Converts the entities of the scene into thread-safe entities that keep
+    /// sharing meshes and materials, see [`to_sync_entities`](fn.to_sync_entities.html).
+    pub fn to_sync_entities(&self) -> Vec<SyncEntity> {
+        to_sync_entities(&self.entities)
+    }
+
+    /// Creates a scene from thread-safe entities, keeping shared meshes and
+    /// materials shared, see [`from_sync_entities`](fn.from_sync_entities.html).
+    pub fn from_sync_entities(entities: &[SyncEntity]) -> Self {
+        from_sync_entities(entities).into()
+    }
+
+    /// Finds the first entity with the given name.
+    pub fn entity(&self, name: &str) -> Option<&Entity> {
+        self.entities.iter().find(|e| e.name == name)
+    }
+
+    /// Finds the first entity with the given name for mutation.
+    pub fn entity_mut(&mut self, name: &str) -> Option<&mut Entity> {
+        self.entities.iter_mut().find(|e| e.name == name)
+    }
+
+    /// Finds the first material in the library with the given name.
+    pub fn material(&self, name: &str) -> Option<&Rc<Material>> {
+        self.materials.iter().find(|m| m.name() == name)
+    }
+
+    /// Iterates over the entities in the order they were added.
+    pub fn entities(&self) -> slice::Iter<'_, Entity> {
+        self.entities.iter()
+    }
+
+    /// Iterates mutably over the entities in the order they were added.
+    ///
+    /// Note that materials set on entities through this iterator are not
+    /// added to the material library.
+    pub fn entities_mut(&mut self) -> slice::IterMut<'_, Entity> {
+        self.entities.iter_mut()
+    }
+
+    /// Iterates over the materials in the material library.
+    pub fn materials(&self) -> slice::Iter<'_, Rc<Material>> {
+        self.materials.iter()
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Calculates a bounding box enclosing the meshes of all entities in world space.
+    pub fn calculate_bounds(&self) -> Aabb {
+        Aabb::from_points(
+            self.entities
+                .iter()
+                .filter(|e| e.vertex_count() > 0)
+                .flat_map(|e| {
+                    let bounds = e.calculate_bounds();
+                    vec![bounds.min, bounds.max]
+                }),
+        )
+    }
+
+    /// Calculates the average of all vertex positions in the scene in world space.
+    pub fn centroid(&self) -> Vec3 {
+        let vertex_count: usize = self.entities.iter().map(|e| e.vertex_count()).sum();
+        let one_over_n = (vertex_count as f32).recip();
+
+        let weighted_sum = self
+            .entities
+            .iter()
+            .filter(|e| e.vertex_count() > 0)
+            .map(|e| e.centroid() * (e.vertex_count() as f32))
+            .sum::<Vec3>();
+
+        one_over_n * weighted_sum
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Vec<Entity>> for Scene {
+    fn from(entities: Vec<Entity>) -> Self {
+        entities.into_iter().collect()
+    }
+}
+
+impl FromIterator<Entity> for Scene {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = Entity>,
+    {
+        let mut scene = Scene::new();
+        for entity in iter {
+            scene.add_entity(entity);
+        }
+        scene
+    }
+}
+
+impl Extend<Entity> for Scene {
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = Entity>,
+    {
+        for entity in iter {
+            self.add_entity(entity);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Scene {
+    type Item = &'a Entity;
+    type IntoIter = slice::Iter<'a, Entity>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.iter()
+    }
+}
+
+impl IntoIterator for Scene {
+    type Item = Entity;
+    type IntoIter = ::std::vec::IntoIter<Entity>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::MaterialBuilder;
+
+    #[test]
+    fn test_material_sharing() {
+        let stone = Rc::new(MaterialBuilder::new().name("Stone").build());
+        let stone_copy = Rc::new(MaterialBuilder::new().name("Stone").build());
+        let metal = Rc::new(MaterialBuilder::new().name("Metal").build());
+
+        let mut scene: Scene = vec![
+            Entity::new(make_mesh(0.0), "A", Rc::clone(&stone)),
+            Entity::new(make_mesh(0.0), "B", stone_copy),
+            Entity::new(make_mesh(0.0), "C", metal),
+        ]
+        .into();
+
+        assert_eq!(scene.entity_count(), 3);
+        assert_eq!(scene.material_count(), 2);
+        assert!(Rc::ptr_eq(&scene.entity("B").unwrap().material, &stone));
+        assert!(Rc::ptr_eq(scene.material("Stone").unwrap(), &stone));
+
+        let removed = scene.remove_entity("C").unwrap();
+        assert_eq!(removed.name, "C");
+        assert!(scene.entity("C").is_none());
+        assert_eq!(scene.material_count(), 2);
+
+        scene.remove_unused_materials();
+        assert_eq!(scene.material_count(), 1);
+        assert!(scene.material("Metal").is_none());
+    }
+
+    #[test]
+    fn test_instances() {
+        let mat = Rc::new(MaterialBuilder::new().build());
+        let mut scene: Scene = vec![Entity::new(make_mesh(0.0), "Tree", mat)].into();
+
+        for i in 1..4 {
+            let transform = Transform::from_translation(Vec3::new(i as f32 * 10.0, 0.0, 0.0));
+            assert!(scene.add_instance("Tree", format!("Tree{}", i), transform));
+        }
+        assert!(!scene.add_instance("Bush", "Bush1", Transform::identity()));
+
+        let autumn = Rc::new(MaterialBuilder::new().name("Autumn").build());
+        assert!(scene.add_instance_with_material(
+            "Tree",
+            "Tree4",
+            Transform::from_translation(Vec3::new(0.0, 0.0, -10.0)),
+            Some(Rc::clone(&autumn)),
+        ));
+        assert!(Rc::ptr_eq(
+            &scene.entity("Tree4").unwrap().material,
+            &autumn
+        ));
+        assert_eq!(scene.material_count(), 2);
+        scene.remove_entity("Tree4");
+
+        let mesh = Rc::clone(&scene.entity("Tree").unwrap().mesh);
+        assert_eq!(scene.entity_count(), 4);
+        assert_eq!(scene.mesh_count(), 1);
+        assert_eq!(scene.instances_of(&mesh).count(), 4);
+        // Held by the four entities and the local
+        assert_eq!(Rc::strong_count(&mesh), 5);
+
+        assert_eq!(scene.triangles().count(), 4);
+
+        let roundtrip = Scene::from_sync_entities(&scene.to_sync_entities());
+        assert_eq!(roundtrip.entity_count(), 4);
+        assert_eq!(roundtrip.mesh_count(), 1);
+        assert_eq!(scene.calculate_bounds().max, Vec3::new(31.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_bounds_and_centroid() {
+        let mat = Rc::new(MaterialBuilder::new().build());
+        let scene: Scene = vec![
+            Entity::new(make_mesh(0.0), "Near", Rc::clone(&mat)),
+            Entity::new(make_mesh(0.0), "Far", Rc::clone(&mat))
+                .with_transform(Transform::from_translation(Vec3::new(10.0, 0.0, 0.0))),
+            Entity::new(DeinterleavedIndexedMeshBuf::default(), "Empty", mat),
+        ]
+        .into();
+
+        let bounds = scene.calculate_bounds();
+        assert_eq!(bounds.min, Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(bounds.max, Vec3::new(11.0, 1.0, 0.0));
+
+        let centroid = scene.centroid();
+        assert!((centroid.x - 16.0 / 3.0).abs() < 0.0001);
+        assert!((centroid.y - 1.0 / 3.0).abs() < 0.0001);
+        assert_eq!(centroid.z, 0.0);
+
+        assert_eq!(scene.entities().count(), 3);
+        assert_eq!((&scene).into_iter().count(), 3);
+    }
+
+    fn make_mesh(offset: f32) -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![offset, 0.0, 0.0, offset + 1.0, 0.0, 0.0, offset, 1.0, 0.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+}
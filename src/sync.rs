@@ -0,0 +1,298 @@
+use entity::Entity;
+use geom::TupleTriangle;
+use material::Material;
+use mesh::{
+    ColoredVertex, DeinterleavedIndexedMeshBuf, DeinterleavedIndexedMeshBufIter, IndexedMesh, Mesh,
+};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use transform::{Transform, TransformedVertices};
+
+/// Thread-safe variant of [`Entity`](struct.Entity.html) that uses `Arc`
+/// instead of `Rc` to share meshes and materials.
+///
+/// Sync entities are `Send` and `Sync`, so they can be shared across threads,
+/// e.g. for parallel tracing of particles through a scene. They are typically
+/// obtained by converting entities with [`to_sync_entities`](fn.to_sync_entities.html)
+/// and converted back with [`from_sync_entities`](fn.from_sync_entities.html).
+///
+/// ```
+/// use aitios_scene::{to_sync_entities, DeinterleavedIndexedMeshBuf, Entity, MaterialBuilder, Mesh};
+/// use std::rc::Rc;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let material = Rc::new(MaterialBuilder::new().build());
+/// let entities = vec![Entity::new(DeinterleavedIndexedMeshBuf::default(), "Empty", material)];
+/// let entities = Arc::new(to_sync_entities(&entities));
+///
+/// let worker_entities = Arc::clone(&entities);
+/// let vertex_count = thread::spawn(move || {
+///     worker_entities.iter().map(|e| e.vertex_count()).sum::<usize>()
+/// }).join().unwrap();
+///
+/// assert_eq!(vertex_count, 0);
+/// ```
+#[derive(Clone)]
+pub struct SyncEntity {
+    pub name: String,
+    /// References the one material associated with this entity.
+    pub material: Arc<Material>,
+    /// The geometry of the entity in model space.
+    pub mesh: Arc<DeinterleavedIndexedMeshBuf>,
+    /// Transforms the mesh from model space into world space.
+    pub transform: Transform,
+}
+
+impl SyncEntity {
+    pub fn new<S: Into<String>>(
+        mesh: DeinterleavedIndexedMeshBuf,
+        name: S,
+        material: Arc<Material>,
+    ) -> Self {
+        SyncEntity {
+            mesh: Arc::new(mesh),
+            name: name.into(),
+            material,
+            transform: Transform::identity(),
+        }
+    }
+
+    /// Sets the model transform, consuming and returning the entity.
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Creates an instance of this entity with a different name and transform,
+    /// sharing mesh and material.
+    pub fn instantiate<S: Into<String>>(&self, name: S, transform: Transform) -> Self {
+        self.instantiate_with_material(name, transform, Arc::clone(&self.material))
+    }
+
+    /// Creates an instance of this entity with a different name, transform
+    /// and material, sharing the mesh.
+    pub fn instantiate_with_material<S: Into<String>>(
+        &self,
+        name: S,
+        transform: Transform,
+        material: Arc<Material>,
+    ) -> Self {
+        SyncEntity {
+            name: name.into(),
+            material,
+            mesh: Arc::clone(&self.mesh),
+            transform,
+        }
+    }
+
+    /// Creates a new mesh in world space by applying the model transform.
+    pub fn bake_transform(&self) -> DeinterleavedIndexedMeshBuf {
+        self.mesh.transformed(&self.transform)
+    }
+}
+
+impl<'a> Mesh<'a> for SyncEntity {
+    type Vertex = ColoredVertex;
+    type VertexIter = TransformedVertices<DeinterleavedIndexedMeshBufIter<'a>>;
+
+    /// Iterates over the vertices of the mesh in world space.
+    fn vertices(&'a self) -> Self::VertexIter {
+        TransformedVertices::new(self.transform, self.mesh.vertices())
+    }
+
+    fn vertex_count(&'a self) -> usize {
+        self.mesh.vertex_count()
+    }
+}
+
+impl<'a> IndexedMesh<'a> for SyncEntity {
+    fn triangle_indices(&'a self, triangle_idx: usize) -> [u32; 3] {
+        self.mesh.triangle_indices(triangle_idx)
+    }
+
+    /// Gets the triangle with the given number in world space.
+    fn triangle_at(&'a self, triangle_idx: usize) -> TupleTriangle<ColoredVertex> {
+        let first = triangle_idx * 3;
+        TupleTriangle::new(
+            self.transform.transform_vertex(self.mesh.vertex_at(first)),
+            self.transform
+                .transform_vertex(self.mesh.vertex_at(first + 1)),
+            self.transform
+                .transform_vertex(self.mesh.vertex_at(first + 2)),
+        )
+    }
+}
+
+/// Converts a single entity, copying mesh and material unless the entity
+/// holds the only reference to them.
+///
+/// Use [`to_sync_entities`](fn.to_sync_entities.html) to convert multiple
+/// entities while keeping them sharing meshes and materials.
+impl From<Entity> for SyncEntity {
+    fn from(entity: Entity) -> Self {
+        SyncEntity {
+            name: entity.name,
+            material: Arc::new(Rc::try_unwrap(entity.material).unwrap_or_else(|m| (*m).clone())),
+            mesh: Arc::new(Rc::try_unwrap(entity.mesh).unwrap_or_else(|m| (*m).clone())),
+            transform: entity.transform,
+        }
+    }
+}
+
+/// Converts a single sync entity, copying mesh and material unless the entity
+/// holds the only reference to them.
+///
+/// Use [`from_sync_entities`](fn.from_sync_entities.html) to convert multiple
+/// entities while keeping them sharing meshes and materials.
+impl From<SyncEntity> for Entity {
+    fn from(entity: SyncEntity) -> Self {
+        Entity {
+            name: entity.name,
+            material: Rc::new(Arc::try_unwrap(entity.material).unwrap_or_else(|m| (*m).clone())),
+            mesh: Rc::new(Arc::try_unwrap(entity.mesh).unwrap_or_else(|m| (*m).clone())),
+            transform: entity.transform,
+        }
+    }
+}
+
+/// Converts entities into thread-safe entities.
+///
+/// Meshes and materials are copied once, entities that shared a mesh or
+/// material before still share it after the conversion.
+pub fn to_sync_entities(entities: &[Entity]) -> Vec<SyncEntity> {
+    let mut materials = SharedCopies::new();
+    let mut meshes = SharedCopies::new();
+
+    entities
+        .iter()
+        .map(|e| SyncEntity {
+            name: e.name.clone(),
+            material: materials.get(&*e.material, Arc::new),
+            mesh: meshes.get(&*e.mesh, Arc::new),
+            transform: e.transform,
+        })
+        .collect()
+}
+
+/// Converts thread-safe entities back into entities.
+///
+/// Meshes and materials are copied once, entities that shared a mesh or
+/// material before still share it after the conversion.
+pub fn from_sync_entities(entities: &[SyncEntity]) -> Vec<Entity> {
+    let mut materials = SharedCopies::new();
+    let mut meshes = SharedCopies::new();
+
+    entities
+        .iter()
+        .map(|e| Entity {
+            name: e.name.clone(),
+            material: materials.get(&*e.material, Rc::new),
+            mesh: meshes.get(&*e.mesh, Rc::new),
+            transform: e.transform,
+        })
+        .collect()
+}
+
+/// Remembers copies of shared values by the address of the original, so
+/// every shared original is only copied once.
+struct SharedCopies<P> {
+    copies: HashMap<usize, P>,
+}
+
+impl<P: Clone> SharedCopies<P> {
+    fn new() -> Self {
+        SharedCopies {
+            copies: HashMap::new(),
+        }
+    }
+
+    fn get<T, F>(&mut self, original: &T, share: F) -> P
+    where
+        T: Clone,
+        F: FnOnce(T) -> P,
+    {
+        let address = original as *const T as usize;
+        self.copies
+            .entry(address)
+            .or_insert_with(|| share(original.clone()))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use geom::{Position, Vec3};
+    use material::MaterialBuilder;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<SyncEntity>();
+    }
+
+    #[test]
+    fn test_conversion_keeps_sharing() {
+        let stone = Rc::new(MaterialBuilder::new().name("Stone").build());
+        let metal = Rc::new(MaterialBuilder::new().name("Metal").build());
+        let wall = Entity::new(make_mesh(), "Wall", Rc::clone(&stone));
+        let entities = vec![
+            wall.instantiate("Wall2", Transform::from_uniform_scale(2.0)),
+            Entity::new(make_mesh(), "Floor", Rc::clone(&stone)),
+            Entity::new(make_mesh(), "Rail", metal),
+            wall,
+        ];
+
+        let sync = to_sync_entities(&entities);
+        assert!(Arc::ptr_eq(&sync[0].mesh, &sync[3].mesh));
+        assert!(!Arc::ptr_eq(&sync[0].mesh, &sync[1].mesh));
+        assert!(Arc::ptr_eq(&sync[0].material, &sync[1].material));
+        assert!(!Arc::ptr_eq(&sync[0].material, &sync[2].material));
+        assert_eq!(sync[0].transform, Transform::from_uniform_scale(2.0));
+
+        let back = from_sync_entities(&sync);
+        assert!(back[0].is_instance_of(&back[3]));
+        assert!(!back[0].is_instance_of(&back[1]));
+        assert!(Rc::ptr_eq(&back[0].material, &back[1].material));
+        assert_eq!(back[2].material.name(), "Metal");
+    }
+
+    #[test]
+    fn test_threads() {
+        let entity = Entity::new(
+            make_mesh(),
+            "Moved",
+            Rc::new(MaterialBuilder::new().build()),
+        )
+        .with_transform(Transform::from_translation(Vec3::new(1.0, 0.0, 0.0)));
+        let entity = Arc::new(SyncEntity::from(entity));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let entity = Arc::clone(&entity);
+                thread::spawn(move || entity.vertices().next().unwrap().position())
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Vec3::new(2.0, 0.0, 0.0));
+        }
+
+        let entity = Entity::from(Arc::try_unwrap(entity).ok().unwrap());
+        assert_eq!(entity.name, "Moved");
+    }
+
+    fn make_mesh() -> DeinterleavedIndexedMeshBuf {
+        DeinterleavedIndexedMeshBuf {
+            positions: vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+            normals: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            texcoords: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+}
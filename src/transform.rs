@@ -0,0 +1,335 @@
+use geom::Vec3;
+use math::{cross, dot, normalize};
+use mesh::ColoredVertex;
+use std::ops::Mul;
+
+/// An affine transformation in 3D space, stored as a column-major 4x4 matrix.
+///
+/// Transforms can be created from translations, rotations and scalings and
+/// combined through multiplication, where the right-hand side is applied first:
+///
+/// ```
+/// # extern crate aitios_geom;
+/// # extern crate aitios_scene;
+/// use aitios_scene::Transform;
+/// use aitios_geom::Vec3;
+///
+/// # fn main() {
+/// let scale_then_move = Transform::from_translation(Vec3::new(10.0, 0.0, 0.0))
+///     * Transform::from_uniform_scale(2.0);
+///
+/// assert_eq!(
+///     scale_then_move.transform_point(Vec3::new(1.0, 1.0, 1.0)),
+///     Vec3::new(12.0, 2.0, 2.0)
+/// );
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Column-major matrix, i.e. `matrix[col][row]`.
+    matrix: [[f32; 4]; 4],
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            matrix: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Creates a transform from a column-major 4x4 matrix, i.e. `matrix[col][row]`.
+    ///
+    /// The last row is expected to be `[0, 0, 0, 1]`, since only affine
+    /// transformations are supported.
+    pub fn from_matrix(matrix: [[f32; 4]; 4]) -> Self {
+        Transform { matrix }
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        let mut transform = Self::identity();
+        transform.matrix[3][0] = translation.x;
+        transform.matrix[3][1] = translation.y;
+        transform.matrix[3][2] = translation.z;
+        transform
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        let mut transform = Self::identity();
+        transform.matrix[0][0] = scale.x;
+        transform.matrix[1][1] = scale.y;
+        transform.matrix[2][2] = scale.z;
+        transform
+    }
+
+    pub fn from_uniform_scale(scale: f32) -> Self {
+        Self::from_scale(Vec3::new(scale, scale, scale))
+    }
+
+    /// Creates a counter-clockwise rotation around the given axis by an angle in radians.
+    pub fn from_axis_angle(axis: Vec3, radians: f32) -> Self {
+        let Vec3 { x, y, z } = normalize(axis);
+        let (sin, cos) = radians.sin_cos();
+        let one_minus_cos = 1.0 - cos;
+
+        Transform {
+            matrix: [
+                [
+                    one_minus_cos * x * x + cos,
+                    one_minus_cos * x * y + sin * z,
+                    one_minus_cos * x * z - sin * y,
+                    0.0,
+                ],
+                [
+                    one_minus_cos * x * y - sin * z,
+                    one_minus_cos * y * y + cos,
+                    one_minus_cos * y * z + sin * x,
+                    0.0,
+                ],
+                [
+                    one_minus_cos * x * z + sin * y,
+                    one_minus_cos * y * z - sin * x,
+                    one_minus_cos * z * z + cos,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Gets the column-major matrix of this transform, i.e. `matrix[col][row]`.
+    pub fn matrix(&self) -> [[f32; 4]; 4] {
+        self.matrix
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Returns the translation part of the transform.
+    pub fn translation(&self) -> Vec3 {
+        self.column(3)
+    }
+
+    /// Calculates the determinant of the linear part of the transform, which is
+    /// negative for transforms that mirror geometry.
+    pub fn determinant(&self) -> f32 {
+        dot(self.column(0), cross(self.column(1), self.column(2)))
+    }
+
+    /// Applies the transform to a position, including translation.
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.transform_vector(point) + self.translation()
+    }
+
+    /// Applies the transform to a direction, ignoring translation.
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        self.column(0) * vector.x + self.column(1) * vector.y + self.column(2) * vector.z
+    }
+
+    /// Transforms a surface normal with the inverse-transpose of the transform
+    /// and normalizes the result, so normals stay perpendicular to surfaces
+    /// even under non-uniform scaling.
+    pub fn transform_normal(&self, normal: Vec3) -> Vec3 {
+        let (x, y, z) = self.normal_basis();
+        normalize(x * normal.x + y * normal.y + z * normal.z)
+    }
+
+    /// Calculates the inverse transform, or `None` if the transform is not invertible.
+    pub fn inverse(&self) -> Option<Transform> {
+        let (a0, a1, a2) = (self.column(0), self.column(1), self.column(2));
+        let det = self.determinant();
+
+        if det.abs() < f32::MIN_POSITIVE {
+            return None;
+        }
+
+        let one_over_det = det.recip();
+        // Rows of the inverse of the linear part
+        let r0 = cross(a1, a2) * one_over_det;
+        let r1 = cross(a2, a0) * one_over_det;
+        let r2 = cross(a0, a1) * one_over_det;
+
+        let t = self.translation();
+        let inv_t = -Vec3::new(dot(r0, t), dot(r1, t), dot(r2, t));
+
+        Some(Transform {
+            matrix: [
+                [r0.x, r1.x, r2.x, 0.0],
+                [r0.y, r1.y, r2.y, 0.0],
+                [r0.z, r1.z, r2.z, 0.0],
+                [inv_t.x, inv_t.y, inv_t.z, 1.0],
+            ],
+        })
+    }
+
+    /// Applies the transform to position and normal of a vertex, leaving
+    /// texture coordinates and color as they are.
+    pub fn transform_vertex<V>(&self, vertex: V) -> ColoredVertex
+    where
+        V: Into<ColoredVertex>,
+    {
+        let vertex = vertex.into();
+        ColoredVertex {
+            position: self.transform_point(vertex.position),
+            normal: self.transform_normal(vertex.normal),
+            ..vertex
+        }
+    }
+
+    fn column(&self, col: usize) -> Vec3 {
+        let c = &self.matrix[col];
+        Vec3::new(c[0], c[1], c[2])
+    }
+
+    /// Columns of the inverse-transpose of the linear part, scaled by the
+    /// absolute value of the determinant. Normalization after transformation
+    /// makes the scale irrelevant, and this works for singular matrices too.
+    fn normal_basis(&self) -> (Vec3, Vec3, Vec3) {
+        let (a0, a1, a2) = (self.column(0), self.column(1), self.column(2));
+        let sign = if self.determinant() < 0.0 { -1.0 } else { 1.0 };
+
+        (
+            cross(a1, a2) * sign,
+            cross(a2, a0) * sign,
+            cross(a0, a1) * sign,
+        )
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Combines two transforms, so that the right-hand side is applied first.
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        let mut matrix = [[0.0; 4]; 4];
+        for (col, out_col) in matrix.iter_mut().enumerate() {
+            for (row, out) in out_col.iter_mut().enumerate() {
+                *out = (0..4)
+                    .map(|k| self.matrix[k][row] * rhs.matrix[col][k])
+                    .sum();
+            }
+        }
+        Transform { matrix }
+    }
+}
+
+/// Iterator adaptor applying a transform to each vertex of another iterator.
+#[derive(Clone)]
+pub struct TransformedVertices<I> {
+    transform: Transform,
+    /// Cached here so it is not recalculated for every vertex
+    normal_basis: (Vec3, Vec3, Vec3),
+    vertices: I,
+}
+
+impl<I> TransformedVertices<I> {
+    pub fn new(transform: Transform, vertices: I) -> Self {
+        TransformedVertices {
+            transform,
+            normal_basis: transform.normal_basis(),
+            vertices,
+        }
+    }
+}
+
+impl<I, V> Iterator for TransformedVertices<I>
+where
+    I: Iterator<Item = V>,
+    V: Into<ColoredVertex>,
+{
+    type Item = ColoredVertex;
+
+    fn next(&mut self) -> Option<ColoredVertex> {
+        self.vertices.next().map(|vtx| {
+            let vtx = vtx.into();
+            let (x, y, z) = self.normal_basis;
+            let normal = vtx.normal;
+
+            ColoredVertex {
+                position: self.transform.transform_point(vtx.position),
+                normal: normalize(x * normal.x + y * normal.y + z * normal.z),
+                ..vtx
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.vertices.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_rotation() {
+        let rot = Transform::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 0.5 * PI);
+        let rotated = rot.transform_point(Vec3::new(1.0, 0.0, 0.0));
+
+        assert_close(rotated, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transform = Transform::from_translation(Vec3::new(1.0, 2.0, 3.0))
+            * Transform::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 1.0)
+            * Transform::from_scale(Vec3::new(2.0, 3.0, 4.0));
+        let inverse = transform.inverse().unwrap();
+        let point = Vec3::new(-4.0, 5.0, 6.0);
+
+        assert_close(
+            inverse.transform_point(transform.transform_point(point)),
+            point,
+        );
+        assert_close(
+            transform.transform_point(inverse.transform_point(point)),
+            point,
+        );
+
+        assert!(Transform::from_scale(Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn test_normal_non_uniform_scale() {
+        // Squash a 45° slope so the surface gets steeper
+        let transform = Transform::from_scale(Vec3::new(1.0, 2.0, 1.0));
+        let tangent = transform.transform_vector(Vec3::new(1.0, -1.0, 0.0));
+        let normal = transform.transform_normal(normalize(Vec3::new(1.0, 1.0, 0.0)));
+
+        assert!(dot(tangent, normal).abs() < 0.0001);
+        assert_close(normal, normalize(Vec3::new(2.0, 1.0, 0.0)));
+
+        // Mirroring should keep normals pointing outward
+        let mirror = Transform::from_scale(Vec3::new(-1.0, 1.0, 1.0));
+        assert_close(
+            mirror.transform_normal(Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(-1.0, 0.0, 0.0),
+        );
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            (actual.x - expected.x).abs() < 0.0001
+                && (actual.y - expected.y).abs() < 0.0001
+                && (actual.z - expected.z).abs() < 0.0001,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}